@@ -0,0 +1,132 @@
+//! The CPU's multiply and divide unit behind $4202-$4206. Results come out
+//! one bit per CPU cycle, 8 cycles for a multiply and 16 for a divide, and
+//! reading $4214-$4217 early sees the partial result. The unit only runs
+//! when it's accessed, catching up from the CPU cycle count.
+
+#[derive(Debug, Clone, Default)]
+pub struct Alu {
+    /// RDDIV: the quotient, or what's left of the multiplier
+    pub rddiv: u16,
+    /// RDMPY: the product, or the remainder
+    pub rdmpy: u16,
+    /// Multiplicand or divisor, moved one bit each step
+    shift: u32,
+    multiply_steps: u8,
+    divide_steps: u8,
+    /// CPU cycle the unit has been stepped up to
+    cycle: u64,
+}
+
+impl Alu {
+    /// Start WRMPYA * WRMPYB, on a write to WRMPYB
+    pub fn multiply(&mut self, wrmpya: u8, wrmpyb: u8, cycle: u64) {
+        self.catch_up(cycle);
+        self.rdmpy = 0;
+        if self.busy() {
+            return;
+        }
+        self.rddiv = u16::from_le_bytes([wrmpya, wrmpyb]);
+        self.shift = wrmpyb as u32;
+        self.multiply_steps = 8;
+    }
+
+    /// Start WRDIV / WRDIVB, on a write to WRDIVB. Dividing by zero gives a
+    /// quotient of $FFFF and leaves the dividend as the remainder.
+    pub fn divide(&mut self, wrdiv: u16, wrdivb: u8, cycle: u64) {
+        self.catch_up(cycle);
+        self.rdmpy = wrdiv;
+        if self.busy() {
+            return;
+        }
+        self.shift = (wrdivb as u32) << 16;
+        self.divide_steps = 16;
+    }
+
+    /// Run the steps that have elapsed by CPU cycle `cycle`
+    pub fn catch_up(&mut self, cycle: u64) {
+        let steps = cycle.saturating_sub(self.cycle);
+        self.cycle += steps;
+        for _ in 0..steps.min(16) {
+            self.step();
+        }
+    }
+
+    /// The unit as it will be at `cycle`, for reads without side effects
+    pub fn at(&self, cycle: u64) -> Alu {
+        let mut alu = self.clone();
+        alu.catch_up(cycle);
+        alu
+    }
+
+    fn busy(&self) -> bool {
+        self.multiply_steps > 0 || self.divide_steps > 0
+    }
+
+    fn step(&mut self) {
+        if self.multiply_steps > 0 {
+            self.multiply_steps -= 1;
+            if self.rddiv & 1 != 0 {
+                self.rdmpy = self.rdmpy.wrapping_add(self.shift as u16);
+            }
+            self.rddiv >>= 1;
+            self.shift <<= 1;
+        }
+        if self.divide_steps > 0 {
+            self.divide_steps -= 1;
+            self.rddiv <<= 1;
+            self.shift >>= 1;
+            if self.rdmpy as u32 >= self.shift {
+                self.rdmpy -= self.shift as u16;
+                self.rddiv |= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod alu_tests {
+    use super::*;
+
+    #[test]
+    fn multiply() {
+        let mut alu = Alu::default();
+        alu.multiply(0xFF, 0xFF, 0);
+        let done = alu.at(8);
+        assert_eq!(done.rdmpy, 0xFE01);
+        // The multiplier is shifted out, leaving WRMPYB
+        assert_eq!(done.rddiv, 0x00FF);
+
+        // Halfway through only the low four bits of WRMPYA are counted
+        alu.multiply(0x35, 0x10, 100);
+        assert_eq!(alu.at(104).rdmpy, 0x05 * 0x10);
+        assert_eq!(alu.at(108).rdmpy, 0x35 * 0x10);
+    }
+
+    #[test]
+    fn divide() {
+        let mut alu = Alu::default();
+        alu.divide(50000, 7, 0);
+        let done = alu.at(16);
+        assert_eq!(done.rddiv, 50000 / 7);
+        assert_eq!(done.rdmpy, 50000 % 7);
+        // The remainder register holds the dividend until bits come out
+        assert_eq!(alu.at(0).rdmpy, 50000);
+        assert_ne!(alu.at(8).rddiv, 50000 / 7);
+
+        alu.divide(0x1234, 0, 16);
+        let done = alu.at(32);
+        assert_eq!(done.rddiv, 0xFFFF);
+        assert_eq!(done.rdmpy, 0x1234);
+    }
+
+    #[test]
+    fn busy_unit_ignores_new_operations() {
+        let mut alu = Alu::default();
+        alu.divide(100, 10, 0);
+        alu.multiply(2, 3, 1);
+        let done = alu.at(16);
+        // The multiply clears RDMPY, so the divide carries on from zero
+        assert_eq!(done.rdmpy, 0);
+        assert_eq!(done.rddiv, 0);
+    }
+}
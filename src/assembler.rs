@@ -0,0 +1,464 @@
+//! Line assembler, the inverse of `decode_instruction`. Takes WDC syntax such
+//! as `LDA #$1234` or `JSL $808000` and picks the addressing mode from the
+//! operand syntax and how many hex digits it was written with, so `$12` is
+//! direct page and `$0012` is absolute.
+
+use crate::cpu::{AddrMode, InstructionContext, OpCode};
+use crate::opcodes::OPCODES;
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum AssemblerError {
+    #[error("Unknown mnemonic {0}")]
+    UnknownMnemonic(String),
+    #[error("Can't parse operand \"{0}\"")]
+    BadOperand(String),
+    #[error("{opcode} has no addressing mode for \"{operand}\"")]
+    NoMode { opcode: OpCode, operand: String },
+    #[error("${value:X} doesn't fit in a {bytes} byte operand")]
+    OutOfRange { value: u32, bytes: usize },
+    #[error("Branch target ${target:04X} is out of range")]
+    BranchRange { target: u32 },
+    #[error("Line {line}: {source}")]
+    Line {
+        line: usize,
+        source: Box<AssemblerError>,
+    },
+}
+
+type Result<T> = std::result::Result<T, AssemblerError>;
+
+/// How many bytes a value was written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Size {
+    Byte,
+    Word,
+    Long,
+}
+
+#[derive(Debug)]
+enum Operand {
+    None,
+    Accumulator,
+    Immediate(u32),
+    Plain(u32, Size),
+    X(u32, Size),
+    Y(u32, Size),
+    Stack(u32),
+    StackY(u32),
+    Indirect(u32, Size),
+    IndirectX(u32, Size),
+    IndirectY(u32),
+    Bracket(u32, Size),
+    BracketY(u32),
+    Move(u32, u32),
+}
+
+/// Parse `$hex`, `%binary` or decimal. Hex and binary are sized by digit count.
+fn parse_value(text: &str) -> Option<(u32, Size)> {
+    let text = text.trim();
+    let (value, bits) = if let Some(hex) = text.strip_prefix('$') {
+        (u32::from_str_radix(hex, 16).ok()?, hex.len() * 4)
+    } else if let Some(binary) = text.strip_prefix('%') {
+        (u32::from_str_radix(binary, 2).ok()?, binary.len())
+    } else {
+        let value: u32 = text.parse().ok()?;
+        (value, 32 - value.leading_zeros() as usize)
+    };
+    let size = match bits {
+        0..=8 => Size::Byte,
+        9..=16 => Size::Word,
+        17..=24 => Size::Long,
+        _ => return None,
+    };
+    Some((value, size))
+}
+
+fn parse_operand(text: &str) -> Option<Operand> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let upper = text.to_uppercase();
+    let value = |s: &str| parse_value(s);
+    let operand = if text.is_empty() {
+        Operand::None
+    } else if upper == "A" {
+        Operand::Accumulator
+    } else if let Some(rest) = text.strip_prefix('#') {
+        Operand::Immediate(value(rest)?.0)
+    } else if let Some(inner) = upper
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(",S),Y"))
+    {
+        Operand::StackY(value(inner)?.0)
+    } else if let Some(inner) = upper.strip_prefix('(').and_then(|s| s.strip_suffix(",X)")) {
+        let (v, size) = value(inner)?;
+        Operand::IndirectX(v, size)
+    } else if let Some(inner) = upper.strip_prefix('(').and_then(|s| s.strip_suffix("),Y")) {
+        Operand::IndirectY(value(inner)?.0)
+    } else if let Some(inner) = upper.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        let (v, size) = value(inner)?;
+        Operand::Indirect(v, size)
+    } else if let Some(inner) = upper.strip_prefix('[').and_then(|s| s.strip_suffix("],Y")) {
+        Operand::BracketY(value(inner)?.0)
+    } else if let Some(inner) = upper.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        let (v, size) = value(inner)?;
+        Operand::Bracket(v, size)
+    } else if let Some(inner) = upper.strip_suffix(",X") {
+        let (v, size) = value(inner)?;
+        Operand::X(v, size)
+    } else if let Some(inner) = upper.strip_suffix(",Y") {
+        let (v, size) = value(inner)?;
+        Operand::Y(v, size)
+    } else if let Some(inner) = upper.strip_suffix(",S") {
+        Operand::Stack(value(inner)?.0)
+    } else if let Some((src, dst)) = upper.split_once(',') {
+        Operand::Move(value(src)?.0, value(dst)?.0)
+    } else {
+        let (v, size) = value(&upper)?;
+        Operand::Plain(v, size)
+    };
+    Some(operand)
+}
+
+/// Addressing modes an operand could mean, most compact first
+fn candidates(operand: &Operand) -> Vec<AddrMode> {
+    use AddrMode::*;
+    let by_size = |size: Size, modes: &[(Size, AddrMode)]| {
+        modes
+            .iter()
+            .filter(|(max, _)| size <= *max)
+            .map(|(_, mode)| mode.clone())
+            .collect()
+    };
+    match *operand {
+        Operand::None => vec![Implied, Accumulator],
+        Operand::Accumulator => vec![Accumulator],
+        // BRK takes its signature byte as an immediate
+        Operand::Immediate(_) => vec![Immediate, Implied],
+        Operand::Plain(_, size) => {
+            // PEA and PER are tabled as immediate but take a bare address
+            let mut modes = vec![RelativeByte, RelativeWord, Immediate];
+            modes.extend(by_size(
+                size,
+                &[
+                    (Size::Byte, Direct),
+                    (Size::Word, Absolute),
+                    (Size::Long, Long),
+                ],
+            ));
+            modes
+        }
+        Operand::X(_, size) => by_size(
+            size,
+            &[
+                (Size::Byte, DirectX),
+                (Size::Word, AbsoluteX),
+                (Size::Long, LongX),
+            ],
+        ),
+        Operand::Y(_, size) => by_size(size, &[(Size::Byte, DirectY), (Size::Word, AbsoluteY)]),
+        Operand::Stack(_) => vec![Stack],
+        Operand::StackY(_) => vec![StackIndexed],
+        // PEI is tabled as direct
+        Operand::Indirect(_, size) => by_size(
+            size,
+            &[
+                (Size::Byte, DirectWord),
+                (Size::Byte, Direct),
+                (Size::Word, AbsoluteIndirectWord),
+                (Size::Word, AbsoluteWord),
+            ],
+        ),
+        Operand::IndirectX(_, size) => by_size(
+            size,
+            &[
+                (Size::Byte, IndexedDirectWord),
+                (Size::Word, AbsoluteIndexedIndirect),
+            ],
+        ),
+        Operand::IndirectY(_) => vec![DirectIndexedWord],
+        Operand::Bracket(_, size) => by_size(
+            size,
+            &[
+                (Size::Byte, DirectSWord),
+                (Size::Word, AbsoluteSWord),
+                (Size::Word, AbsoluteIndirectSWord),
+            ],
+        ),
+        Operand::BracketY(_) => vec![DirectIndexedSWord],
+        Operand::Move(_, _) => vec![SourceDestination],
+    }
+}
+
+fn value_of(operand: &Operand) -> u32 {
+    match *operand {
+        Operand::None | Operand::Accumulator => 0,
+        Operand::Immediate(v)
+        | Operand::Plain(v, _)
+        | Operand::X(v, _)
+        | Operand::Y(v, _)
+        | Operand::Stack(v)
+        | Operand::StackY(v)
+        | Operand::Indirect(v, _)
+        | Operand::IndirectX(v, _)
+        | Operand::IndirectY(v)
+        | Operand::Bracket(v, _)
+        | Operand::BracketY(v) => v,
+        // Destination bank is encoded first
+        Operand::Move(src, dst) => (src & 0xFF) << 8 | (dst & 0xFF),
+    }
+}
+
+/// The opcode byte for a mnemonic in a mode, if the CPU has one
+fn find_opcode(opcode: &OpCode, mode: &AddrMode) -> Option<u8> {
+    OPCODES
+        .iter()
+        .position(|info| info.opcode == *opcode && info.mode == *mode)
+        .map(|byte| byte as u8)
+}
+
+fn parse_mnemonic(name: &str) -> Option<OpCode> {
+    let name = name.to_uppercase();
+    OPCODES
+        .iter()
+        .find(|info| info.opcode.to_string() == name)
+        .map(|info| info.opcode.clone())
+}
+
+/// Assemble one instruction to be placed at `addr`, with the given M and X
+/// widths sizing immediate operands.
+pub fn assemble(line: &str, addr: u32, m: bool, x: bool) -> Result<Vec<u8>> {
+    let line = line.trim();
+    let (name, operand_text) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let opcode =
+        parse_mnemonic(name).ok_or_else(|| AssemblerError::UnknownMnemonic(name.to_string()))?;
+    let operand = parse_operand(operand_text)
+        .ok_or_else(|| AssemblerError::BadOperand(operand_text.trim().to_string()))?;
+    // JMP and JML are interchangeable spellings for the long forms
+    let spellings = match opcode {
+        OpCode::JMP => vec![OpCode::JMP, OpCode::JML],
+        OpCode::JML => vec![OpCode::JML, OpCode::JMP],
+        _ => vec![opcode.clone()],
+    };
+    let value = value_of(&operand);
+    let mut error = None;
+    for mode in candidates(&operand) {
+        if mode == AddrMode::Immediate
+            && matches!(operand, Operand::Plain(..))
+            && !matches!(opcode, OpCode::PEA | OpCode::PER)
+        {
+            continue;
+        }
+        let Some(byte) = spellings.iter().find_map(|op| find_opcode(op, &mode)) else {
+            continue;
+        };
+        let length = InstructionContext {
+            byte,
+            ..Default::default()
+        }
+        .length(m, x);
+        // Implied instructions with a signature byte are the only ones that
+        // take an immediate
+        if mode == AddrMode::Implied && length == 1 && matches!(operand, Operand::Immediate(_)) {
+            continue;
+        }
+        let relative = matches!(mode, AddrMode::RelativeByte | AddrMode::RelativeWord)
+            || OPCODES[byte as usize].opcode == OpCode::PER;
+        let operand_value = if relative {
+            let next = (addr as u16).wrapping_add(length as u16);
+            let offset = (value as u16).wrapping_sub(next) as i16;
+            // Branches stay in the program bank, so a long target has to be in it
+            let other_bank = value > 0xFFFF && value >> 16 != addr >> 16;
+            if other_bank || length == 2 && !(-128..=127).contains(&offset) {
+                error = Some(AssemblerError::BranchRange { target: value });
+                continue;
+            }
+            offset as u16 as u32
+        } else {
+            let bytes = length - 1;
+            if bytes < 3 && value >> (8 * bytes) != 0 {
+                error = Some(AssemblerError::OutOfRange { value, bytes });
+                continue;
+            }
+            value
+        };
+        let mut encoded = vec![byte];
+        encoded.extend(&operand_value.to_le_bytes()[..length - 1]);
+        return Ok(encoded);
+    }
+    Err(error.unwrap_or(AssemblerError::NoMode {
+        opcode,
+        operand: operand_text.trim().to_string(),
+    }))
+}
+
+/// Assemble a block of lines starting at `origin`. Comments start with `;`.
+/// REP and SEP update the widths used for the lines after them.
+pub fn assemble_program(text: &str, origin: u32, mut m: bool, mut x: bool) -> Result<Vec<u8>> {
+    let mut program = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let addr = origin.wrapping_add(program.len() as u32);
+        let bytes = assemble(line, addr, m, x).map_err(|e| AssemblerError::Line {
+            line: i + 1,
+            source: Box::new(e),
+        })?;
+        match bytes[..] {
+            // REP #imm
+            [0xC2, flags] => {
+                m &= flags & 0x20 == 0;
+                x &= flags & 0x10 == 0;
+            }
+            // SEP #imm
+            [0xE2, flags] => {
+                m |= flags & 0x20 != 0;
+                x |= flags & 0x10 != 0;
+            }
+            _ => {}
+        }
+        program.extend(bytes);
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod assembler_tests {
+    use super::*;
+    use crate::cpu;
+    use crate::single_step::flat_console;
+
+    #[test]
+    fn immediate_width_follows_flags() {
+        assert_eq!(assemble("LDA #$12", 0, true, true), Ok(vec![0xA9, 0x12]));
+        assert_eq!(
+            assemble("lda #$1234", 0, false, true),
+            Ok(vec![0xA9, 0x34, 0x12])
+        );
+        assert_eq!(
+            assemble("LDA #$1234", 0, true, true),
+            Err(AssemblerError::OutOfRange {
+                value: 0x1234,
+                bytes: 1
+            })
+        );
+        assert_eq!(
+            assemble("LDX #$1234", 0, true, false),
+            Ok(vec![0xA2, 0x34, 0x12])
+        );
+        assert_eq!(assemble("REP #$30", 0, true, true), Ok(vec![0xC2, 0x30]));
+    }
+
+    #[test]
+    fn operand_size_picks_mode() {
+        assert_eq!(assemble("LDA $12", 0, true, true), Ok(vec![0xA5, 0x12]));
+        assert_eq!(
+            assemble("LDA $0012", 0, true, true),
+            Ok(vec![0xAD, 0x12, 0x00])
+        );
+        assert_eq!(
+            assemble("LDA $7E0012,X", 0, true, true),
+            Ok(vec![0xBF, 0x12, 0x00, 0x7E])
+        );
+        assert_eq!(
+            assemble("JSL $808000", 0, true, true),
+            Ok(vec![0x22, 0x00, 0x80, 0x80])
+        );
+        assert_eq!(
+            assemble("JML $808000", 0, true, true),
+            Ok(vec![0x5C, 0x00, 0x80, 0x80])
+        );
+        assert_eq!(
+            assemble("MVN $7E,$7F", 0, true, true),
+            Ok(vec![0x54, 0x7F, 0x7E])
+        );
+        assert_eq!(assemble("PEI ($12)", 0, true, true), Ok(vec![0xD4, 0x12]));
+        assert_eq!(assemble("BRK", 0, true, true), Ok(vec![0x00, 0x00]));
+        assert_eq!(assemble("ASL", 0, true, true), Ok(vec![0x0A]));
+    }
+
+    #[test]
+    fn branches_are_relative() {
+        assert_eq!(
+            assemble("BRA $8000", 0x8000, true, true),
+            Ok(vec![0x80, 0xFE])
+        );
+        assert_eq!(
+            assemble("BRL $8000", 0x8000, true, true),
+            Ok(vec![0x82, 0xFD, 0xFF])
+        );
+        assert_eq!(
+            assemble("BNE $9000", 0x8000, true, true),
+            Err(AssemblerError::BranchRange { target: 0x9000 })
+        );
+        assert_eq!(
+            assemble("BRA $018000", 0x008000, true, true),
+            Err(AssemblerError::BranchRange { target: 0x018000 })
+        );
+        assert_eq!(
+            assemble("BRL $018000", 0x008000, true, true),
+            Err(AssemblerError::BranchRange { target: 0x018000 })
+        );
+        assert_eq!(
+            assemble("BRA $808000", 0x808000, true, true),
+            Ok(vec![0x80, 0xFE])
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            assemble("FOO $12", 0, true, true),
+            Err(AssemblerError::UnknownMnemonic("FOO".to_string()))
+        );
+        assert!(matches!(
+            assemble("STA #$12", 0, true, true),
+            Err(AssemblerError::NoMode { .. })
+        ));
+        assert!(matches!(
+            assemble_program("NOP\nLDA (", 0, true, true),
+            Err(AssemblerError::Line { line: 2, .. })
+        ));
+    }
+
+    /// Every opcode assembles from the text the disassemblers print for it
+    #[test]
+    fn round_trips_every_opcode() {
+        let mut snes = flat_console();
+        for (byte, info) in OPCODES.iter().enumerate() {
+            let encoded = [byte as u8, 0x12, 0x34, 0x56];
+            snes.ram[0x1000..0x1004].copy_from_slice(&encoded);
+            let instruction = cpu::decode_instruction(&mut snes, byte as u8, 0x001000).unwrap();
+            let length = info.length as usize;
+            let operand = instruction.operand_text(&encoded[1..length], |_| None);
+            let line = format!("{} {}", info.opcode, operand);
+            let bytes = assemble(&line, 0x1000, true, true).unwrap();
+            assert_eq!(bytes, encoded[..length], "{}", line);
+        }
+    }
+
+    #[test]
+    fn program_runs() {
+        let program = assemble_program(
+            "
+            CLC
+            XCE         ; native mode
+            REP #$20
+            LDA #$1234  ; 16 bit now
+            STA $2000
+            ",
+            0x1000,
+            true,
+            true,
+        )
+        .unwrap();
+        let mut snes = flat_console();
+        snes.ram[0x1000..0x1000 + program.len()].copy_from_slice(&program);
+        snes.cpu.PC = 0x1000;
+        for _ in 0..5 {
+            cpu::step(&mut snes).unwrap();
+        }
+        assert_eq!(snes.ram[0x2000..0x2002], [0x34, 0x12]);
+    }
+}
@@ -2,9 +2,13 @@ use color_eyre::{
     eyre::{bail, eyre, Context},
     Result,
 };
-use log::{debug, info};
+use log::{debug, info, warn};
 use num_enum::TryFromPrimitive;
-use std::{fs, path::Path, str};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str,
+};
 
 #[derive(Clone, Debug)]
 pub enum MapMode {
@@ -31,6 +35,27 @@ pub enum ExtraHardware {
     RomCoprocessorBattery,
 }
 
+impl ExtraHardware {
+    pub fn has_ram(&self) -> bool {
+        matches!(
+            self,
+            ExtraHardware::RomRam
+                | ExtraHardware::RomRamBattery
+                | ExtraHardware::RomCoprocessorRam
+                | ExtraHardware::RomCoprocessorRamBattery
+        )
+    }
+
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self,
+            ExtraHardware::RomRamBattery
+                | ExtraHardware::RomCoprocessorRamBattery
+                | ExtraHardware::RomCoprocessorBattery
+        )
+    }
+}
+
 #[derive(Clone, TryFromPrimitive, Debug)]
 #[repr(u8)]
 pub enum Coprocessor {
@@ -110,7 +135,7 @@ pub struct RomHeader {
     pub extra_hardware: CartHardware,
     /// 1<<N Kilobytes, here stored as size in bytes
     pub rom_size: usize,
-    /// 1<<N Kilobytes, here stored as size in bytes
+    /// 1<<N Kilobytes, here stored as size in bytes. 0 when N is 0
     pub ram_size: usize,
     pub country: Region,
     pub developer_id: u8,
@@ -125,10 +150,19 @@ pub struct RomHeader {
 pub struct Cartridge {
     pub header: RomHeader,
     pub rom_data: Vec<u8>,
+    /// Cartridge RAM, empty if the board has none
+    pub sram: Vec<u8>,
 }
 
-fn load_rom_header(file: &Vec<u8>, bypass_checksum: bool) -> Result<RomHeader> {
-    if file.len() % 1024 != 0 {
+impl Cartridge {
+    /// SRAM survives power off and should be saved
+    pub fn has_battery(&self) -> bool {
+        self.header.extra_hardware.extra_hardware.has_battery() && !self.sram.is_empty()
+    }
+}
+
+fn load_rom_header(file: &[u8], bypass_checksum: bool) -> Result<RomHeader> {
+    if !file.len().is_multiple_of(1024) {
         debug!("Rom Dumper header found in file.");
     }
 
@@ -206,7 +240,7 @@ fn load_rom_header(file: &Vec<u8>, bypass_checksum: bool) -> Result<RomHeader> {
         .with_context(|| format!("Unknown Hardware {:02X}", header_slice[0x16] & 0xF))?;
 
     let coprocessor = match header_slice[0x16] & 0x0F {
-        3 | 4 | 5 | 6 => Some(
+        3..=6 => Some(
             Coprocessor::try_from((header_slice[0x16] & 0xF0) >> 4).with_context(|| {
                 format!(
                     "Unknown Coprocessor {:02X}",
@@ -226,7 +260,10 @@ fn load_rom_header(file: &Vec<u8>, bypass_checksum: bool) -> Result<RomHeader> {
 
     let rom_size = (1usize << header_slice[0x17]) * 1024;
 
-    let ram_size = (1usize << header_slice[0x18]) * 1024;
+    let ram_size = match header_slice[0x18] {
+        0 => 0,
+        n => (1usize << n) * 1024,
+    };
 
     debug!(
         "ROM size: {:}kB, RAM size: {:}kB",
@@ -323,25 +360,68 @@ fn load_rom_header(file: &Vec<u8>, bypass_checksum: bool) -> Result<RomHeader> {
         expanded_header,
     };
 
-    return Ok(header);
+    Ok(header)
 }
 
 pub fn load_rom(rom_file: &Path, bypass_checksum: bool) -> Result<Cartridge> {
-    let file: Vec<u8> = fs::read(&rom_file)
+    let file: Vec<u8> = fs::read(rom_file)
         .wrap_err_with(|| format!("Failed to read rom file {}", rom_file.display()))?;
 
     let header = load_rom_header(&file, bypass_checksum)?;
 
+    let sram_size = if header.extra_hardware.extra_hardware.has_ram() {
+        header.ram_size
+    } else {
+        0
+    };
+
     let cart = Cartridge {
         header,
         rom_data: file.clone(),
+        sram: vec![0; sram_size],
     };
 
-    return Ok(cart);
+    Ok(cart)
+}
+
+/// Battery save file that sits next to the ROM
+pub fn sram_path(rom_file: &Path) -> PathBuf {
+    rom_file.with_extension("srm")
+}
+
+/// Fill SRAM from a battery save, if the cartridge has a battery and the
+/// save exists
+pub fn load_sram(cartridge: &mut Cartridge, srm_file: &Path) -> Result<()> {
+    if !cartridge.has_battery() || !srm_file.exists() {
+        return Ok(());
+    }
+    let save = fs::read(srm_file)
+        .wrap_err_with(|| format!("Failed to read save file {}", srm_file.display()))?;
+    if save.len() != cartridge.sram.len() {
+        warn!(
+            "Save file is {} bytes, cartridge has {} bytes of SRAM",
+            save.len(),
+            cartridge.sram.len()
+        );
+    }
+    let len = save.len().min(cartridge.sram.len());
+    cartridge.sram[..len].copy_from_slice(&save[..len]);
+    info!("Loaded save from {}", srm_file.display());
+    Ok(())
+}
+
+/// Write SRAM out to a battery save. Does nothing without a battery.
+pub fn save_sram(cartridge: &Cartridge, srm_file: &Path) -> Result<()> {
+    if !cartridge.has_battery() {
+        return Ok(());
+    }
+    fs::write(srm_file, &cartridge.sram)
+        .wrap_err_with(|| format!("Failed to write save file {}", srm_file.display()))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
 
     // Helper function to create a minimal valid LoROM header
     fn create_lorom_header() -> Vec<u8> {
@@ -450,4 +530,55 @@ mod tests {
         // Verify hardware type
         assert_eq!(header[0x7FD6], 0x00);
     }
+
+    /// Write the ROM to a temporary directory with a correct checksum
+    fn write_rom(dir: &Path, mut rom: Vec<u8>) -> PathBuf {
+        // The checksum and complement bytes always add up to $1FE
+        rom[0x7FDC..0x7FE0].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00]);
+        let checksum = rom
+            .iter()
+            .fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+        rom[0x7FDC..0x7FDE].copy_from_slice(&(checksum ^ 0xFFFF).to_le_bytes());
+        rom[0x7FDE..0x7FE0].copy_from_slice(&checksum.to_le_bytes());
+        let rom_file = dir.join("test.sfc");
+        fs::write(&rom_file, &rom).unwrap();
+        rom_file
+    }
+
+    #[test]
+    fn test_sram_round_trip() {
+        let mut rom = create_lorom_header();
+        rom[0x7FD6] = 0x02; // ROM, RAM and battery
+        rom[0x7FD8] = 0x03; // 8KB
+        let dir = tempfile::tempdir().unwrap();
+        let rom_file = write_rom(dir.path(), rom);
+
+        let mut cartridge = load_rom(&rom_file, false).unwrap();
+        assert_eq!(cartridge.sram.len(), 8 * 1024);
+        assert!(cartridge.has_battery());
+
+        let srm_file = sram_path(&rom_file);
+        assert_eq!(srm_file, dir.path().join("test.srm"));
+        // No save yet is not an error
+        load_sram(&mut cartridge, &srm_file).unwrap();
+
+        cartridge.sram[0x1234] = 0x56;
+        save_sram(&cartridge, &srm_file).unwrap();
+
+        let mut reloaded = load_rom(&rom_file, false).unwrap();
+        load_sram(&mut reloaded, &srm_file).unwrap();
+        assert_eq!(reloaded.sram, cartridge.sram);
+    }
+
+    #[test]
+    fn test_no_sram_without_ram() {
+        let rom = create_lorom_header();
+        let dir = tempfile::tempdir().unwrap();
+        let rom_file = write_rom(dir.path(), rom);
+
+        let cartridge = load_rom(&rom_file, false).unwrap();
+        assert_eq!(cartridge.header.ram_size, 0);
+        assert!(cartridge.sram.is_empty());
+        assert!(!cartridge.has_battery());
+    }
 }
@@ -1,76 +1,32 @@
-use crate::memory::{self, read_byte, read_word, write_byte, write_word};
+use crate::memory::{self, Bus, MemoryError};
+use crate::opcodes::{self, OpcodeInfo, OPCODES};
 
 use super::Console;
-use color_eyre::{eyre::bail, eyre::Ok, Result};
 use log::trace;
-
-const OLD_OPCODES: [OpCode; 65] = [
-    OpCode::LDA,
-    OpCode::LDX,
-    OpCode::LDY,
-    OpCode::STA,
-    OpCode::STX,
-    OpCode::STY,
-    OpCode::STZ,
-    OpCode::PHA,
-    OpCode::PHX,
-    OpCode::PHY,
-    OpCode::PHP,
-    OpCode::PLA,
-    OpCode::PLX,
-    OpCode::PLY,
-    OpCode::PLP,
-    OpCode::TSX,
-    OpCode::TXS,
-    OpCode::INX,
-    OpCode::INY,
-    OpCode::DEX,
-    OpCode::DEY,
-    OpCode::INC,
-    OpCode::DEC,
-    OpCode::ASL,
-    OpCode::LSR,
-    OpCode::ROL,
-    OpCode::ROR,
-    OpCode::AND,
-    OpCode::ORA,
-    OpCode::EOR,
-    OpCode::BIT,
-    OpCode::CMP,
-    OpCode::CPX,
-    OpCode::CPY,
-    OpCode::TRB,
-    OpCode::TSB,
-    OpCode::ADC,
-    OpCode::SBC,
-    OpCode::JMP,
-    OpCode::JSR,
-    OpCode::RTS,
-    OpCode::RTI,
-    OpCode::BRA,
-    OpCode::BEQ,
-    OpCode::BNE,
-    OpCode::BCC,
-    OpCode::BCS,
-    OpCode::BVC,
-    OpCode::BVS,
-    OpCode::BMI,
-    OpCode::BPL,
-    OpCode::CLC,
-    OpCode::CLD,
-    OpCode::CLI,
-    OpCode::CLV,
-    OpCode::SEC,
-    OpCode::SED,
-    OpCode::SEI,
-    OpCode::TAX,
-    OpCode::TAY,
-    OpCode::TXA,
-    OpCode::TYA,
-    OpCode::NOP,
-    OpCode::BRK,
-    OpCode::PEI,
-];
+use thiserror::Error;
+
+type Result<T, E = MemoryError> = std::result::Result<T, E>;
+
+/// Failure kinds reported while decoding or executing an instruction. Each
+/// carries the address of the offending instruction.
+#[derive(Debug, Error)]
+pub enum CpuError {
+    #[error("Failed to fetch instruction at ${pc:06X}")]
+    Fetch { pc: u32, source: MemoryError },
+    #[error("Failed to execute {opcode} {mode} at ${pc:06X}")]
+    Memory {
+        pc: u32,
+        opcode: OpCode,
+        mode: AddrMode,
+        source: MemoryError,
+    },
+    #[error("Failed to enter {kind:?} handler from ${pc:06X}")]
+    Interrupt {
+        pc: u32,
+        kind: Interrupt,
+        source: MemoryError,
+    },
+}
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub enum OpCode {
@@ -196,67 +152,6 @@ impl OpCode {
     pub fn is_return(&self) -> bool {
         matches!(self, OpCode::RTS | OpCode::RTI | OpCode::RTL)
     }
-    pub fn is_old(&self) -> bool {
-        OLD_OPCODES.contains(self)
-    }
-    pub fn changes_x(&self) -> bool {
-        matches!(self, OpCode::PLP | OpCode::RTI | OpCode::REP | OpCode::SEP)
-    }
-    pub fn changes_m(&self) -> bool {
-        matches!(self, OpCode::PLP | OpCode::RTI | OpCode::REP | OpCode::SEP)
-    }
-    fn uses_x(&self) -> bool {
-        matches!(
-            self,
-            OpCode::CPX
-                | OpCode::CPY
-                | OpCode::DEX
-                | OpCode::DEY
-                | OpCode::INX
-                | OpCode::INY
-                | OpCode::LDX
-                | OpCode::LDY
-                | OpCode::STX
-                | OpCode::STY
-                | OpCode::PHX
-                | OpCode::PHY
-                | OpCode::PLX
-                | OpCode::PLY
-                | OpCode::TAX
-                | OpCode::TAY
-                | OpCode::TSX
-                | OpCode::TXS
-                | OpCode::TXY
-                | OpCode::TYX
-        )
-    }
-    fn uses_m(&self) -> bool {
-        matches!(
-            self,
-            OpCode::ADC
-                | OpCode::SBC
-                | OpCode::AND
-                | OpCode::EOR
-                | OpCode::ORA
-                | OpCode::ASL
-                | OpCode::LSR
-                | OpCode::ROL
-                | OpCode::ROR
-                | OpCode::BIT
-                | OpCode::CMP
-                | OpCode::DEC
-                | OpCode::INC
-                | OpCode::LDA
-                | OpCode::STA
-                | OpCode::STZ
-                | OpCode::PHA
-                | OpCode::PLA
-                | OpCode::TRB
-                | OpCode::TSB
-                | OpCode::TXA
-                | OpCode::TYA
-        )
-    }
 }
 
 impl std::fmt::Display for OpCode {
@@ -415,9 +310,9 @@ impl std::fmt::Display for AddrMode {
             AddrMode::AbsoluteSWord => "[Absolute]",
             AddrMode::AbsoluteX => "Absolute, X",
             AddrMode::AbsoluteY => "Absolute, Y",
-            AddrMode::AbsoluteIndirectWord => "(Absolute, X)",
-            AddrMode::AbsoluteIndirectSWord => todo!(),
-            AddrMode::AbsoluteIndexedIndirect => todo!(),
+            AddrMode::AbsoluteIndirectWord => "(Absolute)",
+            AddrMode::AbsoluteIndirectSWord => "[Absolute]",
+            AddrMode::AbsoluteIndexedIndirect => "(Absolute, X)",
             AddrMode::Accumulator => "Accumulator",
             AddrMode::Direct => "Direct",
             AddrMode::DirectX => "Direct, X",
@@ -465,6 +360,41 @@ pub struct Flags {
     pub b: bool,
 }
 
+/// Operand size selected by the M or X flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    Byte,
+    Word,
+}
+
+impl Width {
+    /// Byte when the width flag is set, word when it is clear
+    pub fn from_flag(flag: bool) -> Width {
+        if flag {
+            Width::Byte
+        } else {
+            Width::Word
+        }
+    }
+    pub fn mask(self) -> u16 {
+        match self {
+            Width::Byte => 0x00FF,
+            Width::Word => 0xFFFF,
+        }
+    }
+    pub fn sign(self) -> u16 {
+        match self {
+            Width::Byte => 0x0080,
+            Width::Word => 0x8000,
+        }
+    }
+    /// Replace the active part of a register. In 8 bit mode the high byte
+    /// (B for the accumulator) is left alone.
+    pub fn merge(self, reg: u16, value: u16) -> u16 {
+        (reg & !self.mask()) | (value & self.mask())
+    }
+}
+
 #[derive(Clone, Debug)]
 #[allow(non_snake_case)]
 /// The 65C816 CPU
@@ -487,6 +417,26 @@ pub struct CPU {
     pub P: Flags,
     /// Program Counter (16 bit)
     pub PC: u16,
+    /// NMI edge latched, taken before the next instruction
+    pub nmi_pending: bool,
+    /// IRQ line level, taken before the next instruction unless I is set
+    pub irq_pending: bool,
+    /// Running, or halted by WAI/STP
+    pub state: RunState,
+    /// CPU cycles run since power on. Bus accesses count as they happen, so
+    /// this is exact partway through an instruction up to internal cycles.
+    pub cycles: u64,
+}
+
+/// Whether the CPU is executing, halted by WAI or halted by STP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunState {
+    #[default]
+    Running,
+    /// Halted by WAI until an NMI or IRQ is asserted
+    Waiting,
+    /// Halted by STP until reset
+    Stopped,
 }
 
 impl Flags {
@@ -537,6 +487,10 @@ impl CPU {
             K: 0,
             P: Flags::new(),
             PC: 0,
+            nmi_pending: false,
+            irq_pending: false,
+            state: RunState::Running,
+            cycles: 0,
         }
     }
     pub fn p_byte(&self) -> u8 {
@@ -561,8 +515,13 @@ impl CPU {
         self.P.v = (p & 0b01000000) != 0;
         self.P.n = (p & 0b10000000) != 0;
         if self.P.e {
+            self.P.m = true;
             self.P.x = true;
         }
+        if self.P.x {
+            self.X &= 0x00FF;
+            self.Y &= 0x00FF;
+        }
     }
     pub fn get_pc(&self) -> u32 {
         self.PC as u32 | (self.K as u32) << 16
@@ -571,12 +530,48 @@ impl CPU {
         self.PC = (addr & 0xFFFF) as u16;
         self.K = addr.to_be_bytes()[1];
     }
+    /// Width of the accumulator and memory operands
+    pub fn m_width(&self) -> Width {
+        Width::from_flag(self.P.m)
+    }
+    /// Width of the index registers
+    pub fn x_width(&self) -> Width {
+        Width::from_flag(self.P.x)
+    }
+    fn set_nz(&mut self, value: u16, width: Width) {
+        self.P.n = value & width.sign() != 0;
+        self.P.z = value & width.mask() == 0;
+    }
+    /// Load the accumulator at the M width, setting N and Z
+    fn set_a(&mut self, value: u16) {
+        let width = self.m_width();
+        self.A = width.merge(self.A, value);
+        self.set_nz(value, width);
+    }
+    /// Load X at the X width, setting N and Z
+    fn set_x(&mut self, value: u16) {
+        let width = self.x_width();
+        self.X = width.merge(self.X, value);
+        self.set_nz(value, width);
+    }
+    /// Load Y at the X width, setting N and Z
+    fn set_y(&mut self, value: u16) {
+        let width = self.x_width();
+        self.Y = width.merge(self.Y, value);
+        self.set_nz(value, width);
+    }
+    /// CMP, CPX and CPY
+    fn compare(&mut self, reg: u16, data: u16, width: Width) {
+        let reg = reg & width.mask();
+        self.P.c = reg >= data;
+        self.set_nz(reg.wrapping_sub(data), width);
+    }
 }
 
 impl std::fmt::Display for CPU {
     #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "A:   {:04X}\nX:   {:04X}\nY:   {:04X}\nS:   {:04X}\nDBR: {:04X}\nD:   {:04X}\nK:   {:02X}\nP:   {}\nPC:  ${:04X}",
+        write!(f, "A:   {:04X}\nX:   {:04X}\nY:   {:04X}\nS:   {:04X}\nDBR: {:04X}\nD:   {:04X}\nK:   {:02X}\nP:   {}\nPC:  ${:04X}\n{:?}",
             self.A,
             self.X,
             self.Y,
@@ -585,66 +580,104 @@ impl std::fmt::Display for CPU {
             self.D,
             self.K,
             self.P,
-            self.PC
+            self.PC,
+            self.state
         )
     }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct InstructionContext {
+    /// The opcode byte, indexing `OPCODES`
+    pub byte: u8,
     pub opcode: OpCode,
     pub mode: AddrMode,
     pub inst_addr: u32,
     pub data_addr: u32,
-    pub dest_addr: Option<u32>,
+    /// Destination of a block move, zero in every other mode
+    pub dest_addr: u32,
+    /// Indexing moved the effective address onto another page
+    pub crossed_page: bool,
 }
 
 impl InstructionContext {
     pub fn with_source<'a>(&'a self, snes: &'a Console) -> InstructionContextWrapper<'a> {
         InstructionContextWrapper {
             context: self,
-            snes: snes,
+            snes,
         }
     }
+    pub fn info(&self) -> &'static OpcodeInfo {
+        &OPCODES[self.byte as usize]
+    }
+    /// Length in bytes. Immediate operands sized by M or X grow by a byte when
+    /// that register is 16 bit.
     pub fn length(&self, m: bool, x: bool) -> usize {
-        match self.opcode {
-            OpCode::REP | OpCode::SEP => return 2,
-            OpCode::PEA | OpCode::PER => return 3,
-            _ => {}
+        let info = self.info();
+        let mut length = info.length as usize;
+        if info.mode == AddrMode::Immediate {
+            length += (info.penalties & opcodes::M16 != 0 && !m) as usize;
+            length += (info.penalties & opcodes::X16 != 0 && !x) as usize;
         }
+        length
+    }
+    /// Operand in assembler syntax, from the bytes that follow the opcode.
+    /// `label` may name the target of jumps, branches, PER and long
+    /// addresses, which are otherwise written as numbers.
+    pub fn operand_text(&self, bytes: &[u8], label: impl Fn(u32) -> Option<String>) -> String {
+        let value = bytes
+            .iter()
+            .rev()
+            .fold(0u32, |acc, &byte| acc << 8 | byte as u32);
+        let byte = format!("${:02X}", value);
+        let word = format!("${:04X}", value);
+        let long = format!("${:06X}", value);
+        let target = match self.opcode {
+            // PER's operand is the immediate offset, not where it points
+            OpCode::PER => {
+                self.inst_addr & 0xFF0000
+                    | (self.inst_addr as u16)
+                        .wrapping_add(3)
+                        .wrapping_add(value as u16) as u32
+            }
+            _ => self.data_addr,
+        };
+        let label_or = |fallback: String| label(target).unwrap_or(fallback);
         match self.mode {
-            AddrMode::Absolute => 3,
-            AddrMode::AbsoluteWord => 3,
-            AddrMode::AbsoluteSWord => 3,
-            AddrMode::AbsoluteX | AddrMode::AbsoluteY => 3,
-            AddrMode::AbsoluteIndirectWord => 3,
-            AddrMode::AbsoluteIndirectSWord => todo!(),
-            AddrMode::AbsoluteIndexedIndirect => todo!(),
-            AddrMode::Accumulator => 1,
-            AddrMode::Direct => 2,
-            AddrMode::DirectX | AddrMode::DirectY => 2,
-            AddrMode::DirectWord => 3,
-            AddrMode::DirectSWord => 2,
-            AddrMode::IndexedDirectWord => 2,
-            AddrMode::DirectIndexedWord => 2,
-            AddrMode::DirectIndexedSWord => 2,
-            AddrMode::Immediate => {
-                if self.opcode.uses_m() {
-                    3 - (m as usize)
-                } else if self.opcode.uses_x() {
-                    3 - (x as usize)
-                } else {
-                    3
-                }
-            }
-            AddrMode::Implied => 1,
-            AddrMode::Long => 4,
-            AddrMode::LongX => 4,
-            AddrMode::RelativeByte => 2,
-            AddrMode::RelativeWord => 3,
-            AddrMode::SourceDestination => 3,
-            AddrMode::Stack => 2,
-            AddrMode::StackIndexed => 2,
+            AddrMode::Implied if bytes.len() == 1 => format!("#{}", byte),
+            AddrMode::Implied => String::new(),
+            AddrMode::Accumulator => "A".to_string(),
+            AddrMode::Immediate => match self.opcode {
+                OpCode::PEA => word,
+                OpCode::PER => label_or(format!("${:04X}", target & 0xFFFF)),
+                _ if bytes.len() == 1 => format!("#{}", byte),
+                _ => format!("#{}", word),
+            },
+            AddrMode::Absolute if self.opcode.is_jump() => label_or(word),
+            AddrMode::Absolute => word,
+            AddrMode::AbsoluteX => format!("{},X", word),
+            AddrMode::AbsoluteY => format!("{},Y", word),
+            AddrMode::AbsoluteWord | AddrMode::AbsoluteIndirectWord => format!("({})", word),
+            AddrMode::AbsoluteSWord | AddrMode::AbsoluteIndirectSWord => format!("[{}]", word),
+            AddrMode::AbsoluteIndexedIndirect => format!("({},X)", word),
+            AddrMode::Direct if self.opcode == OpCode::PEI => format!("({})", byte),
+            AddrMode::Direct => byte,
+            AddrMode::DirectX => format!("{},X", byte),
+            AddrMode::DirectY => format!("{},Y", byte),
+            AddrMode::DirectWord => format!("({})", byte),
+            AddrMode::DirectSWord => format!("[{}]", byte),
+            AddrMode::IndexedDirectWord => format!("({},X)", byte),
+            AddrMode::DirectIndexedWord => format!("({}),Y", byte),
+            AddrMode::DirectIndexedSWord => format!("[{}],Y", byte),
+            AddrMode::Long => label_or(long),
+            AddrMode::LongX => format!("{},X", long),
+            AddrMode::RelativeByte | AddrMode::RelativeWord => {
+                label_or(format!("${:04X}", target & 0xFFFF))
+            }
+            // The destination bank is encoded first
+            AddrMode::SourceDestination => format!("${:02X},${:02X}", value >> 8, value & 0xFF),
+            AddrMode::Stack => format!("{},S", byte),
+            AddrMode::StackIndexed => format!("({},S),Y", byte),
         }
     }
 }
@@ -665,7 +698,7 @@ impl<'a> std::fmt::Display for InstructionContextWrapper<'a> {
                     self.context.inst_addr,
                     self.context.opcode,
                     self.context.data_addr,
-                    self.context.dest_addr.unwrap()
+                    self.context.dest_addr
                 )
             }
             AddrMode::Accumulator | AddrMode::Implied => {
@@ -675,41 +708,23 @@ impl<'a> std::fmt::Display for InstructionContextWrapper<'a> {
                     self.context.inst_addr, self.context.opcode
                 )
             }
-            AddrMode::Immediate => match &self.context.opcode {
-                OpCode::REP | OpCode::SEP | OpCode::WDM => {
-                    let data = memory::peek_byte(self.snes, self.context.data_addr).unwrap();
+            AddrMode::Immediate => {
+                if self.context.length(self.snes.cpu.P.m, self.snes.cpu.P.x) == 2 {
+                    let data = self.snes.peek(self.context.data_addr).unwrap_or(0);
                     write!(
                         f,
                         "${:06X}: {} #{:02X}",
                         self.context.inst_addr, self.context.opcode, data
                     )
-                }
-                OpCode::PEA | OpCode::PER => {
-                    let data = memory::peek_word(self.snes, self.context.data_addr).unwrap();
+                } else {
+                    let data = self.snes.peek_word(self.context.data_addr).unwrap_or(0);
                     write!(
                         f,
                         "${:06X}: {} #{:04X}",
                         self.context.inst_addr, self.context.opcode, data
                     )
                 }
-                _ => {
-                    if self.snes.cpu.P.m {
-                        let data = memory::peek_byte(self.snes, self.context.data_addr).unwrap();
-                        write!(
-                            f,
-                            "${:06X}: {} #{:02X}",
-                            self.context.inst_addr, self.context.opcode, data
-                        )
-                    } else {
-                        let data = memory::peek_word(self.snes, self.context.data_addr).unwrap();
-                        write!(
-                            f,
-                            "${:06X}: {} #{:04X}",
-                            self.context.inst_addr, self.context.opcode, data
-                        )
-                    }
-                }
-            },
+            }
             _ => {
                 write!(
                     f,
@@ -729,578 +744,442 @@ pub enum CPUExecutionResult {
     Subroutine(u32),
     Return,
     Interrupt,
+    /// Nothing executed because the CPU is waiting or stopped
+    Idle,
 }
 
-pub fn decode_addressing_mode(opcode: u8) -> Result<AddrMode> {
-    let bbb = (opcode & 0b00011100) >> 2;
-    let cc = opcode & 0b00000011;
+/// True when two addresses sit on different pages
+fn page_crossed(from: u32, to: u32) -> bool {
+    (from ^ to) & 0xFFFF00 != 0
+}
 
+/// Whether a branch instruction will be taken with the current flags
+fn branch_condition(cpu: &CPU, opcode: &OpCode) -> bool {
     match opcode {
-        0x00 | 0x08 | 0x0B | 0x18 | 0x1B | 0x28 | 0x2B | 0x38 | 0x3B | 0x40 | 0x48 | 0x4B
-        | 0x58 | 0x5A | 0x5B | 0x60 | 0x68 | 0x6B | 0x78 | 0x7A | 0x7B | 0x88 | 0x8A | 0x8B
-        | 0x98 | 0x9A | 0x9B | 0xA8 | 0xAA | 0xAB | 0xB8 | 0xBA | 0xBB | 0xC8 | 0xCA | 0xCB
-        | 0xD8 | 0xDA | 0xDB | 0xE8 | 0xEA | 0xEB | 0xF8 | 0xFA | 0xFB => {
-            return Ok(AddrMode::Implied)
-        } // Single byte instructions
-        0x0A | 0x1A | 0x2A | 0x3A | 0x4A | 0x6A => return Ok(AddrMode::Accumulator), // ASL A, INC A, ROL A, DEC A, LSR A, ROR A
-        0x14 | 0x64 | 0xD4 => return Ok(AddrMode::Direct), // TRB zp, STZ zp, PEI dir
-        0x1C | 0x20 | 0x9C => return Ok(AddrMode::Absolute), // TRB abs, JSR abs, STZ abs
-        0x22 | 0x5C => return Ok(AddrMode::Long),          // JMP long,
-        0x44 | 0x52 => return Ok(AddrMode::SourceDestination), // MVN src,dest, MVP src,dest
-        0xDC => return Ok(AddrMode::AbsoluteSWord),
-        0x6C => return Ok(AddrMode::AbsoluteIndirectWord), // JMP (abs)
-        0x74 => return Ok(AddrMode::DirectX),              // STZ zp,X
-        0x7C | 0xFC => return Ok(AddrMode::AbsoluteIndexedIndirect), // JMP (abs,X), JSR (abs,X)
-        0x10 | 0x30 | 0x50 | 0x70 | 0x80 | 0x90 | 0xB0 | 0xD0 | 0xF0 => {
-            return Ok(AddrMode::RelativeByte)
-        } // BRA rel8
-        0x82 => return Ok(AddrMode::RelativeWord),         // BRL rel16
-        0x02 | 0x42 | 0x62 | 0x89 | 0xC2 | 0xE2 | 0xF4 => return Ok(AddrMode::Immediate), // COP immed, PER immed, BIT immed, REP immed, SEP immed, PEA immed
-        0x9E => return Ok(AddrMode::AbsoluteX), // STZ abs,X
-        _ => (),
-    }
-
-    let mode: AddrMode = match cc {
-        0b00 => match bbb {
-            0b000 => AddrMode::Immediate,
-            0b001 => AddrMode::Direct,
-            0b010 => bail!("Unknown opcode {:02X}", opcode),
-            0b011 => AddrMode::Absolute,
-            0b100 => bail!("Unknown opcode {:02X}", opcode),
-            0b101 => AddrMode::DirectX,
-            0b110 => bail!("Unknown opcode {:02X}", opcode),
-            0b111 => AddrMode::AbsoluteX,
-            _ => unreachable!(),
-        },
-        0b01 => match bbb {
-            0b000 => AddrMode::IndexedDirectWord,
-            0b001 => AddrMode::Direct,
-            0b010 => AddrMode::Immediate,
-            0b011 => AddrMode::Absolute,
-            0b100 => AddrMode::DirectIndexedWord,
-            0b101 => AddrMode::DirectX,
-            0b110 => AddrMode::AbsoluteY,
-            0b111 => AddrMode::AbsoluteX,
-            _ => unreachable!(),
-        },
-        0b10 => match bbb {
-            0b000 => AddrMode::Immediate,
-            0b001 => AddrMode::Direct,
-            0b010 => AddrMode::Accumulator,
-            0b011 => AddrMode::Absolute,
-            0b100 => AddrMode::DirectWord,
-            0b101 => AddrMode::DirectX,
-            0b110 => bail!("Unknown opcode {:02X}", opcode),
-            0b111 => AddrMode::AbsoluteX,
-            _ => unreachable!(),
-        },
-        0b11 => match bbb {
-            0b000 => AddrMode::Stack,
-            0b001 => AddrMode::DirectSWord,
-            0b010 => bail!("Unknown opcode {:02X}", opcode),
-            0b011 => AddrMode::Long,
-            0b100 => AddrMode::StackIndexed,
-            0b101 => AddrMode::DirectIndexedSWord,
-            0b110 => bail!("Unknown opcode {:02X}", opcode),
-            0b111 => AddrMode::LongX,
-            _ => unreachable!(),
-        },
-        _ => {
-            unreachable!()
-        }
-    };
-    Ok(mode)
+        OpCode::BCC => !cpu.P.c,
+        OpCode::BCS => cpu.P.c,
+        OpCode::BEQ => cpu.P.z,
+        OpCode::BNE => !cpu.P.z,
+        OpCode::BMI => cpu.P.n,
+        OpCode::BPL => !cpu.P.n,
+        OpCode::BVC => !cpu.P.v,
+        OpCode::BVS => cpu.P.v,
+        _ => true,
+    }
 }
 
-fn calculate_cycles(snes: &Console, instruction: InstructionContext) -> Result<u8> {
-    let w = ((snes.cpu.D & 0xFF) > 1) as u8;
-    let p = ((instruction.data_addr & 0xFF0000) >> 16) as u8 != snes.cpu.K;
-    let m = snes.cpu.P.m as u8;
-    let cycles = match instruction.opcode {
-        OpCode::JMP | OpCode::JML => match instruction.mode {
-            AddrMode::Absolute => 3,
-            AddrMode::Long => 4,
-            AddrMode::AbsoluteIndirectWord => 5,
-            AddrMode::AbsoluteIndexedIndirect | AddrMode::AbsoluteIndirectSWord => 6,
-            _ => unreachable!(),
-        },
-        OpCode::JSL | OpCode::JSR => match instruction.mode {
-            AddrMode::Long => 8,
-            AddrMode::Absolute => 6,
-            AddrMode::AbsoluteIndexedIndirect => 8,
-            _ => unreachable!(),
-        },
-        OpCode::RTL | OpCode::RTS => 6,
-        OpCode::RTI => 7 - snes.cpu.P.e as u8,
-        OpCode::MVN | OpCode::MVP => 7,
-        OpCode::NOP | OpCode::WDM => 2,
-        OpCode::PEA => 5,
-        OpCode::PER => 6,
-        OpCode::PEI => 6 + w,
-        OpCode::ADC | OpCode::SBC => match instruction.mode {
-            AddrMode::IndexedDirectWord => 7 - m + w,
-            AddrMode::Stack => 5 - m,
-            AddrMode::Direct => 4 - m + w,
-            AddrMode::DirectSWord => 7 - m + w,
-            AddrMode::Immediate => 3 - m,
-            AddrMode::Absolute => 5 - m,
-            AddrMode::Long => 6 - m,
-            AddrMode::DirectIndexedWord => {
-                7 - m + w - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            AddrMode::DirectWord => 6 - m + w,
-            AddrMode::StackIndexed => 8 - m,
-            AddrMode::DirectX => 5 - m + w,
-            AddrMode::DirectIndexedSWord => 7 - m + w,
-            AddrMode::AbsoluteY | AddrMode::AbsoluteX => {
-                6 - m - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            _ => unreachable!(),
-        },
-        OpCode::CMP => match instruction.mode {
-            AddrMode::IndexedDirectWord => 7 - m + w,
-            AddrMode::Stack => 5 - m,
-            AddrMode::Direct => 4 - m + w,
-            AddrMode::DirectSWord => 7 - m + w,
-            AddrMode::Immediate => 3 - m,
-            AddrMode::Absolute => 5 - m,
-            AddrMode::DirectIndexedWord => {
-                7 - m + w - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            AddrMode::DirectWord => 6 - m + w,
-            AddrMode::StackIndexed => 8 - m,
-            AddrMode::DirectX => 5 - m + w,
-            AddrMode::DirectIndexedSWord => 7 - m + w,
-            AddrMode::AbsoluteY | AddrMode::AbsoluteX => {
-                6 - m - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            AddrMode::LongX => 6 - m,
-            _ => unreachable!(),
-        },
-        OpCode::CPX | OpCode::CPY => match instruction.mode {
-            AddrMode::Immediate => 3 - snes.cpu.P.x as u8,
-            AddrMode::Direct => 4 - snes.cpu.P.x as u8 + w,
-            AddrMode::Absolute => 5 - snes.cpu.P.x as u8,
-            _ => unreachable!(),
-        },
-        OpCode::DEC | OpCode::DEX | OpCode::DEY | OpCode::INC | OpCode::INX | OpCode::INY => {
-            match instruction.mode {
-                AddrMode::Accumulator => 2,
-                AddrMode::Direct => 7 - (2 * m) + w,
-                AddrMode::Absolute => 8 - (2 * m),
-                AddrMode::DirectX => 8 - (2 * m) + w,
-                AddrMode::AbsoluteX => 9 - (2 * m),
-                AddrMode::Implied => 2,
-                _ => unreachable!(),
-            }
-        }
-        OpCode::AND | OpCode::EOR | OpCode::ORA => match instruction.mode {
-            AddrMode::IndexedDirectWord => 7 - m + w,
-            AddrMode::Stack => 5 - m,
-            AddrMode::Direct => 4 - m + w,
-            AddrMode::DirectSWord => 7 - m + w,
-            AddrMode::Immediate => 3 - m,
-            AddrMode::Absolute => 5 - m,
-            AddrMode::Long => 6 - m,
-            AddrMode::DirectIndexedWord => {
-                7 - m + w - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            AddrMode::DirectWord => 6 - m + w,
-            AddrMode::StackIndexed => 8 - m,
-            AddrMode::DirectX => 5 - m + w,
-            AddrMode::DirectIndexedSWord => 7 - m + w,
-            AddrMode::AbsoluteY | AddrMode::AbsoluteX => {
-                6 - m - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            AddrMode::LongX => 6 - m,
-            _ => unreachable!(),
-        },
-        OpCode::BIT => match instruction.mode {
-            AddrMode::Direct => 4 - m + w,
-            AddrMode::Absolute => 5 - m,
-            AddrMode::DirectX => 5 - m + w,
-            AddrMode::AbsoluteX => 6 - m - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8,
-            AddrMode::Immediate => 3 - m,
-            _ => unreachable!(),
-        },
-        OpCode::TRB | OpCode::TSB => match instruction.mode {
-            AddrMode::Direct => 7 - (2 * m) + w,
-            AddrMode::Absolute => 8 - (2 * m),
-            _ => unreachable!(),
-        },
-        OpCode::ASL | OpCode::LSR | OpCode::ROL | OpCode::ROR => match instruction.mode {
-            AddrMode::Direct => 7 - (2 * m) + w,
-            AddrMode::Accumulator => 2,
-            AddrMode::Absolute => 8 - (2 * m),
-            AddrMode::DirectX => 8 - (2 * m) + w,
-            AddrMode::AbsoluteX => 9 - (2 * m),
-            _ => unreachable!(),
-        },
-        OpCode::BRA => 3 + (snes.cpu.P.e || p) as u8,
-        OpCode::BCC => 2 + (!snes.cpu.P.c as u8) + (!snes.cpu.P.c || snes.cpu.P.e || p) as u8,
-        OpCode::BCS => 2 + (snes.cpu.P.c as u8) + (snes.cpu.P.c || snes.cpu.P.e || p) as u8,
-        OpCode::BEQ => 2 + (snes.cpu.P.z as u8) + (snes.cpu.P.z || snes.cpu.P.e || p) as u8,
-        OpCode::BMI => 2 + (snes.cpu.P.n as u8) + (snes.cpu.P.n || snes.cpu.P.e || p) as u8,
-        OpCode::BNE => 2 + (!snes.cpu.P.n as u8) + (!snes.cpu.P.n || snes.cpu.P.e || p) as u8,
-        OpCode::BPL => 2 + (!snes.cpu.P.z as u8) + (!snes.cpu.P.z || snes.cpu.P.e || p) as u8,
-        OpCode::BVC => 2 + (snes.cpu.P.v as u8) + (snes.cpu.P.v || snes.cpu.P.e || p) as u8,
-        OpCode::BVS => 2 + (!snes.cpu.P.v as u8) + (!snes.cpu.P.v || snes.cpu.P.e || p) as u8,
-        OpCode::BRL => 4,
-        OpCode::BRK | OpCode::COP => 8 - snes.cpu.P.e as u8,
-        OpCode::CLC
-        | OpCode::CLD
-        | OpCode::CLI
-        | OpCode::CLV
-        | OpCode::SEC
-        | OpCode::SED
-        | OpCode::SEI => 2,
-        OpCode::REP | OpCode::SEP => 3,
-        OpCode::LDA | OpCode::STA => match instruction.mode {
-            AddrMode::IndexedDirectWord => 7 - m + w,
-            AddrMode::Stack => 5 - m,
-            AddrMode::Direct => 4 - m + w,
-            AddrMode::DirectSWord => 7 - m + w,
-            AddrMode::Immediate => 3 - m,
-            AddrMode::Absolute => 5 - m,
-            AddrMode::Long => 6 - m,
-            AddrMode::DirectIndexedWord => {
-                7 - m + w - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            AddrMode::DirectWord => 6 - m + w,
-            AddrMode::StackIndexed => 8 - m,
-            AddrMode::DirectX => 5 - m + w,
-            AddrMode::DirectIndexedSWord => 7 - m + w,
-            AddrMode::AbsoluteY | AddrMode::AbsoluteX => {
-                6 - m - snes.cpu.P.x as u8 + (snes.cpu.P.x || p) as u8
-            }
-            _ => unreachable!(),
-        },
-        OpCode::LDX | OpCode::LDY | OpCode::STX | OpCode::STY => match instruction.mode {
-            AddrMode::Immediate => 3 - snes.cpu.P.x as u8,
-            AddrMode::Direct => 4 - snes.cpu.P.x as u8 + w,
-            AddrMode::Absolute => 5 - snes.cpu.P.x as u8,
-            AddrMode::DirectY | AddrMode::DirectX => 5 - snes.cpu.P.x as u8 + w,
-            AddrMode::AbsoluteY | AddrMode::AbsoluteX => {
-                6 - (2 * snes.cpu.P.x as u8) + (snes.cpu.P.x || p) as u8
-            }
-            _ => unreachable!(),
-        },
-        OpCode::STZ => match instruction.mode {
-            AddrMode::Direct => 4 - m + w,
-            AddrMode::DirectX => 5 - m + w,
-            AddrMode::Absolute => 5 - m,
-            AddrMode::AbsoluteX => 6 - m,
-            _ => unreachable!(),
-        },
-        OpCode::PHA => 4 - m,
-        OpCode::PHX | OpCode::PHY => 4 - snes.cpu.P.x as u8,
-        OpCode::PLA => 5 - m,
-        OpCode::PLX | OpCode::PLY => 5 - snes.cpu.P.x as u8,
-        OpCode::PHB | OpCode::PHK | OpCode::PHP => 3,
-        OpCode::PHD | OpCode::PLB | OpCode::PLP => 4,
-        OpCode::PLD => 5,
-        OpCode::STP | OpCode::WAI => 3,
-        OpCode::TAX
-        | OpCode::TAY
-        | OpCode::TSX
-        | OpCode::TXA
-        | OpCode::TXS
-        | OpCode::TXY
-        | OpCode::TYA
-        | OpCode::TYX
-        | OpCode::TCD
-        | OpCode::TCS
-        | OpCode::TDC
-        | OpCode::TSC => 2,
-        OpCode::XBA => 3,
-        OpCode::XCE => 2,
+/// Number of CPU cycles an instruction takes. Must be called with the state
+/// from before the instruction executes.
+pub fn calculate_cycles(snes: &Console, instruction: &InstructionContext) -> u8 {
+    let cpu = &snes.cpu;
+    let info = instruction.info();
+    let has = |penalty: u8| info.penalties & penalty != 0;
+    // Indexing costs a cycle when it crosses a page, or always with 16 bit
+    // index registers
+    let index_penalty = !cpu.P.x || instruction.crossed_page;
+    let mut cycles = info.cycles;
+    cycles += (has(opcodes::M16) && !cpu.P.m) as u8;
+    cycles += 2 * (has(opcodes::RMW16) && !cpu.P.m) as u8;
+    cycles += (has(opcodes::X16) && !cpu.P.x) as u8;
+    cycles += (has(opcodes::DL) && cpu.D & 0xFF != 0) as u8;
+    cycles += (has(opcodes::IX | opcodes::IY) && index_penalty) as u8;
+    cycles += (has(opcodes::NATIVE) && !cpu.P.e) as u8;
+    if has(opcodes::BRANCH) && branch_condition(cpu, &instruction.opcode) {
+        // Emulation mode pays another cycle for a taken branch to a new page
+        let next = instruction.inst_addr.wrapping_add(2);
+        cycles += 1 + (cpu.P.e && page_crossed(next, instruction.data_addr)) as u8;
+    }
+    cycles
+}
+
+/// Master clocks an instruction takes. Each bus access is charged at the speed
+/// of the region it hits and the cycles left over are internal operations,
+/// which always take 6 clocks. Must be called with the state from before the
+/// instruction executes.
+fn calculate_master_cycles(snes: &Console, instruction: &InstructionContext, cycles: u8) -> u64 {
+    let cpu = &snes.cpu;
+    let m = 2 - cpu.P.m as u64;
+    let x = 2 - cpu.P.x as u64;
+    let e = cpu.P.e as u64;
+    let stack = if cpu.P.e {
+        0x0100 | (cpu.S & 0xFF) as u32
+    } else {
+        cpu.S as u32
+    };
+    let direct = cpu.D as u32;
+    let mut master = 0;
+    let mut accesses = 0;
+    let mut charge = |addr: u32, count: u64| {
+        master += memory::access_speed(snes, addr) as u64 * count;
+        accesses += count;
     };
-    Ok(cycles)
+
+    charge(
+        instruction.inst_addr,
+        instruction.length(cpu.P.m, cpu.P.x) as u64,
+    );
+
+    // Indirect modes fetch their pointer first
+    match instruction.mode {
+        AddrMode::DirectWord | AddrMode::IndexedDirectWord | AddrMode::DirectIndexedWord => {
+            charge(direct, 2)
+        }
+        AddrMode::DirectSWord | AddrMode::DirectIndexedSWord => charge(direct, 3),
+        AddrMode::StackIndexed => charge(stack, 2),
+        AddrMode::AbsoluteIndirectWord
+        | AddrMode::AbsoluteIndirectSWord
+        | AddrMode::AbsoluteSWord
+        | AddrMode::AbsoluteIndexedIndirect => {
+            let pointer = snes
+                .peek_word(instruction.inst_addr.wrapping_add(1))
+                .unwrap_or_default() as u32;
+            let bank = match instruction.mode {
+                AddrMode::AbsoluteIndexedIndirect => instruction.inst_addr & 0xFF0000,
+                _ => 0,
+            };
+            let count = match instruction.mode {
+                AddrMode::AbsoluteIndirectSWord | AddrMode::AbsoluteSWord => 3,
+                _ => 2,
+            };
+            charge(bank | pointer, count);
+        }
+        _ => {}
+    }
+
+    let operand = !matches!(
+        instruction.mode,
+        AddrMode::Immediate | AddrMode::Implied | AddrMode::Accumulator
+    );
+    match instruction.opcode {
+        OpCode::ADC
+        | OpCode::SBC
+        | OpCode::AND
+        | OpCode::EOR
+        | OpCode::ORA
+        | OpCode::BIT
+        | OpCode::CMP
+        | OpCode::LDA
+        | OpCode::STA
+        | OpCode::STZ
+            if operand =>
+        {
+            charge(instruction.data_addr, m)
+        }
+        OpCode::CPX | OpCode::CPY | OpCode::LDX | OpCode::LDY | OpCode::STX | OpCode::STY
+            if operand =>
+        {
+            charge(instruction.data_addr, x)
+        }
+        OpCode::ASL
+        | OpCode::LSR
+        | OpCode::ROL
+        | OpCode::ROR
+        | OpCode::DEC
+        | OpCode::INC
+        | OpCode::TRB
+        | OpCode::TSB
+            if operand =>
+        {
+            charge(instruction.data_addr, 2 * m)
+        }
+        OpCode::MVN | OpCode::MVP => {
+            charge(instruction.data_addr, 1);
+            charge(instruction.dest_addr, 1);
+        }
+        OpCode::PHA | OpCode::PLA => charge(stack, m),
+        OpCode::PHX | OpCode::PHY | OpCode::PLX | OpCode::PLY => charge(stack, x),
+        OpCode::PHB | OpCode::PHK | OpCode::PHP | OpCode::PLB | OpCode::PLP => charge(stack, 1),
+        OpCode::PHD | OpCode::PLD | OpCode::PEA | OpCode::PER | OpCode::JSR | OpCode::RTS => {
+            charge(stack, 2)
+        }
+        OpCode::PEI => {
+            charge(direct, 2);
+            charge(stack, 2);
+        }
+        OpCode::JSL | OpCode::RTL => charge(stack, 3),
+        OpCode::RTI => charge(stack, 4 - e),
+        OpCode::BRK | OpCode::COP => {
+            charge(stack, 4 - e);
+            charge(0x00FFE0, 2);
+        }
+        _ => {}
+    }
+
+    let internal = (cycles as u64).saturating_sub(accesses);
+    master + internal * 6
+}
+
+/// Resolve a direct page offset to a bank 0 address. In emulation mode with DL
+/// at zero, 6502 era instructions wrap inside the direct page.
+fn direct_address(cpu: &CPU, offset: u32, wrap_page: bool) -> u32 {
+    if wrap_page && cpu.P.e && (cpu.D & 0xFF) == 0x00 {
+        (cpu.D & 0xFF00) as u32 | (offset & 0xFF)
+    } else {
+        (cpu.D as u32 + offset) & 0xFFFF
+    }
+}
+
+/// Read a 16 bit pointer from the direct page, wrapping like `direct_address`
+fn read_direct_pointer(cpu: &CPU, offset: u32, fetch: &mut impl Fetch) -> Result<u16> {
+    let low = fetch(direct_address(cpu, offset, true))?;
+    let high = fetch(direct_address(cpu, offset + 1, true))?;
+    Ok(u16::from_be_bytes([high, low]))
+}
+
+/// Read a 16 or 24 bit pointer at `addr`, wrapping inside the bank
+fn read_bank_pointer(bank: u32, addr: u16, long: bool, fetch: &mut impl Fetch) -> Result<u32> {
+    let count = if long { 3 } else { 2 };
+    let mut pointer = 0;
+    for i in 0..count {
+        let byte = fetch(bank | addr.wrapping_add(i) as u32)?;
+        pointer |= (byte as u32) << (8 * i);
+    }
+    Ok(pointer)
 }
 
+/// Add an index register to a 24 bit base address. Returns the address,
+/// wrapped to 24 bits, and whether it landed on a different page.
+fn index_address(base: u32, index: u16) -> (u32, bool) {
+    let addr = base.wrapping_add(index as u32) & 0xFFFFFF;
+    (addr, page_crossed(base, addr))
+}
+
+/// How decoding reads operand bytes and pointers: bus reads when running,
+/// peeks when looking ahead
+trait Fetch: FnMut(u32) -> Result<u8> {}
+impl<F: FnMut(u32) -> Result<u8>> Fetch for F {}
+
+/// Effective address of an instruction at `loc`, the destination for block
+/// moves, and whether indexing crossed a page. Operand bytes and immediates
+/// wrap inside the program bank, direct page and stack relative addresses
+/// wrap inside bank 0, and indexed data addresses carry into the next bank.
 fn calculate_address(
-    snes: &Console,
-    op: &OpCode,
-    mode: &AddrMode,
+    cpu: &CPU,
+    info: &OpcodeInfo,
     loc: u32,
-) -> Result<(u32, Option<u32>)> {
-    let l = memory::peek_byte(snes, loc + 1)? as u32;
-    let h = memory::peek_byte(snes, loc + 2)? as u32;
-    let mut dest = None;
-    let addr = match mode {
+    fetch: &mut impl Fetch,
+) -> Result<(u32, u32, bool)> {
+    let program_bank = loc & 0xFF0000;
+    let program = |offset: u16| program_bank | (loc as u16).wrapping_add(offset) as u32;
+    // Operand bytes are bus reads like the opcode fetch, so hooks and the MDR
+    // see them. Immediate operands are read when the instruction executes.
+    let operand_bytes = match info.mode {
+        AddrMode::Immediate | AddrMode::Accumulator | AddrMode::Implied => 0,
+        _ => info.length as u16 - 1,
+    };
+    let mut operand = [0u32; 3];
+    for i in 0..operand_bytes {
+        operand[i as usize] = fetch(program(i + 1))? as u32;
+    }
+    let [l, h, hh] = operand;
+    let data_bank = (cpu.DBR as u32) << 16;
+    let mut dest = 0;
+    let mut crossed = false;
+    let addr = match info.mode {
         AddrMode::Absolute => {
-            if op.is_jump() {
-                ((snes.cpu.K as u32) << 16) | h << 8 | l
-            } else {
-                ((snes.cpu.DBR as u32) << 16) | h << 8 | l
-            }
-        }
-        AddrMode::AbsoluteWord => h << 8 | l,
-        AddrMode::AbsoluteSWord => h << 8 | l,
-        AddrMode::AbsoluteX => {
-            let refaddr = ((snes.cpu.DBR as u32) << 16) | h << 8 | l;
-            refaddr + snes.cpu.X as u32
-        }
-        AddrMode::AbsoluteY => {
-            let refaddr = ((snes.cpu.DBR as u32) << 16) | h << 8 | l;
-            refaddr + snes.cpu.Y as u32
-        }
-        AddrMode::AbsoluteIndirectWord => {
-            let refaddr = h << 8 | l;
-            (snes.cpu.K as u32) << 16 | memory::read_word(snes, refaddr)? as u32
-        }
-        AddrMode::AbsoluteIndirectSWord => {
-            let refaddr = h << 8 | l;
-            let low = memory::read_word(snes, refaddr)? as u32;
-            let high = memory::read_byte(snes, refaddr + 2)? as u32;
-            high << 16 | low
-        }
-        AddrMode::AbsoluteIndexedIndirect => {
-            let refaddr = ((snes.cpu.K as u32) << 16) | h << 8 | l;
-            refaddr + snes.cpu.X as u32
-        }
-        AddrMode::Accumulator => snes.cpu.get_pc(),
-        AddrMode::Direct => {
-            if snes.cpu.P.e && (snes.cpu.D & 0xFF) == 0x00 && op.is_old() {
-                ((snes.cpu.D & 0xFF00) as u32) >> 8 | l
-            } else {
-                (snes.cpu.D as u32) + l
-            }
-        }
-        AddrMode::DirectX => {
-            if snes.cpu.P.e && (snes.cpu.D & 0xFF) == 0x00 {
-                let templ = (snes.cpu.X as u8).wrapping_add(l as u8);
-                ((snes.cpu.D & 0xFF00) as u32) >> 8 | templ as u32
+            if info.opcode.is_jump() {
+                program_bank | h << 8 | l
             } else {
-                (snes.cpu.D as u32) + l + snes.cpu.X as u32
+                data_bank | h << 8 | l
             }
         }
-        AddrMode::DirectY => {
-            if snes.cpu.P.e && (snes.cpu.D & 0xFF) == 0x00 {
-                let templ = (snes.cpu.Y as u8).wrapping_add(l as u8);
-                ((snes.cpu.D & 0xFF00) as u32) >> 8 | templ as u32
+        AddrMode::AbsoluteX | AddrMode::AbsoluteY => {
+            let index = if info.mode == AddrMode::AbsoluteX {
+                cpu.X
             } else {
-                (snes.cpu.D as u32) + l + snes.cpu.Y as u32
-            }
+                cpu.Y
+            };
+            let (addr, page) = index_address(data_bank | h << 8 | l, index);
+            crossed = page;
+            addr
         }
-        AddrMode::DirectWord => {
-            if snes.cpu.P.e && (snes.cpu.D & 0xFF) == 0x00 {
-                let temp_addr = ((snes.cpu.D & 0xFF00) as u32) >> 8 | l;
-                let pointer = memory::read_word(snes, temp_addr)?;
-                (snes.cpu.DBR as u32) << 16 | pointer as u32
-            } else {
-                let temp_addr = (snes.cpu.D as u32) + l;
-                let pointer = memory::read_word(snes, temp_addr)?;
-                (snes.cpu.DBR as u32) << 16 | pointer as u32
-            }
+        AddrMode::AbsoluteWord | AddrMode::AbsoluteIndirectWord => {
+            let pointer = read_bank_pointer(0, (h << 8 | l) as u16, false, fetch)?;
+            program_bank | pointer
         }
-        AddrMode::DirectSWord => {
-            let temp_addr = (snes.cpu.D as u32) + l;
-            let pointer = memory::read_word(snes, temp_addr)?;
-            (snes.cpu.DBR as u32) << 16 | pointer as u32
+        AddrMode::AbsoluteSWord | AddrMode::AbsoluteIndirectSWord => {
+            read_bank_pointer(0, (h << 8 | l) as u16, true, fetch)?
         }
+        AddrMode::AbsoluteIndexedIndirect => {
+            let pointer_addr = ((h << 8 | l) as u16).wrapping_add(cpu.X);
+            program_bank | read_bank_pointer(program_bank, pointer_addr, false, fetch)?
+        }
+        AddrMode::Accumulator | AddrMode::Implied => loc,
+        AddrMode::Direct => direct_address(cpu, l, info.legacy),
+        AddrMode::DirectX => direct_address(cpu, l + cpu.X as u32, true),
+        AddrMode::DirectY => direct_address(cpu, l + cpu.Y as u32, true),
+        AddrMode::DirectWord => data_bank | read_direct_pointer(cpu, l, fetch)? as u32,
+        AddrMode::DirectSWord => read_bank_pointer(0, (cpu.D as u32 + l) as u16, true, fetch)?,
         AddrMode::IndexedDirectWord => {
-            if snes.cpu.P.e && (snes.cpu.D & 0xFF) == 0x00 {
-                let temp_addr = ((snes.cpu.D & 0xFF00) as u32) >> 8 | l;
-                let pointer = memory::read_word(snes, temp_addr.wrapping_add(snes.cpu.X as u32))?;
-                (snes.cpu.DBR as u32) << 16 | pointer as u32
-            } else {
-                let temp_addr = (snes.cpu.D as u32) + l;
-                let pointer = memory::read_word(snes, temp_addr.wrapping_add(snes.cpu.X as u32))?;
-                (snes.cpu.DBR as u32) << 16 | pointer as u32
-            }
+            data_bank | read_direct_pointer(cpu, l + cpu.X as u32, fetch)? as u32
         }
         AddrMode::DirectIndexedWord => {
-            if snes.cpu.P.e && (snes.cpu.D & 0xFF) == 0x00 {
-                let temp_addr = ((snes.cpu.D & 0xFF00) as u32) >> 8 | l;
-                let pointer = memory::read_word(snes, temp_addr)?;
-                let temp_data_addr = (snes.cpu.DBR as u32) << 16 | pointer as u32;
-                temp_data_addr.wrapping_add(snes.cpu.Y as u32)
-            } else {
-                let temp_addr = (snes.cpu.D as u32) + l;
-                let pointer = memory::read_word(snes, temp_addr)?;
-                let temp_data_addr = (snes.cpu.DBR as u32) << 16 | pointer as u32;
-                temp_data_addr.wrapping_add(snes.cpu.Y as u32)
-            }
+            let pointer = read_direct_pointer(cpu, l, fetch)?;
+            let (addr, page) = index_address(data_bank | pointer as u32, cpu.Y);
+            crossed = page;
+            addr
         }
         AddrMode::DirectIndexedSWord => {
-            let temp_addr = (snes.cpu.D as u32) + l;
-            let pointer_lo = memory::read_byte(snes, temp_addr)?;
-            let pointer_mid = memory::read_byte(snes, temp_addr + 1)?;
-            let pointer_hi = memory::read_byte(snes, temp_addr + 2)?;
-            let temp_data_addr = u32::from_be_bytes([0x00, pointer_hi, pointer_mid, pointer_lo]);
-            temp_data_addr.wrapping_add(snes.cpu.Y as u32)
-        }
-        AddrMode::Immediate => snes.cpu.get_pc() + 1,
-        AddrMode::Implied => snes.cpu.get_pc(),
-        AddrMode::Long => {
-            let hh = memory::read_byte(snes, snes.cpu.get_pc() + 3)?;
-            u32::from_be_bytes([0x00, hh, h as u8, l as u8])
+            let pointer = read_bank_pointer(0, (cpu.D as u32 + l) as u16, true, fetch)?;
+            let (addr, page) = index_address(pointer, cpu.Y);
+            crossed = page;
+            addr
         }
+        AddrMode::Immediate => program(1),
+        AddrMode::Long => hh << 16 | h << 8 | l,
         AddrMode::LongX => {
-            let hh = memory::read_byte(snes, snes.cpu.get_pc() + 3)?;
-            let temp = u32::from_be_bytes([0x00, hh, h as u8, l as u8]);
-            temp + snes.cpu.X as u32
-        }
-        AddrMode::RelativeByte => {
-            let temp = if l <= 0x7F {
-                snes.cpu.PC + 2 + l as u16
-            } else {
-                snes.cpu.PC - 254 + l as u16
-            }
-            .to_be_bytes();
-            u32::from_be_bytes([0x00, snes.cpu.K, temp[0], temp[1]])
-        }
-        AddrMode::RelativeWord => {
-            let temp = snes
-                .cpu
-                .PC
-                .wrapping_add(3)
-                .wrapping_add(u16::from_be_bytes([h as u8, l as u8]))
-                .to_be_bytes();
-            u32::from_be_bytes([0x00, snes.cpu.K, temp[0], temp[1]])
+            let (addr, page) = index_address(hh << 16 | h << 8 | l, cpu.X);
+            crossed = page;
+            addr
         }
+        AddrMode::RelativeByte => program(2u16.wrapping_add(l as i8 as u16)),
+        AddrMode::RelativeWord => program(3u16.wrapping_add((h << 8 | l) as u16)),
         AddrMode::SourceDestination => {
-            dest = Some(l << 16 | snes.cpu.X as u32);
-            h << 16 | snes.cpu.Y as u32
+            // Operand bytes are destination bank, then source bank
+            dest = l << 16 | cpu.Y as u32;
+            h << 16 | cpu.X as u32
         }
-        AddrMode::Stack => (l as u16 + snes.cpu.S) as u32,
+        AddrMode::Stack => cpu.S.wrapping_add(l as u16) as u32,
         AddrMode::StackIndexed => {
-            let low = memory::read_byte(snes, (l as u16 + snes.cpu.S) as u32)?;
-            let high = memory::read_byte(snes, (l as u16 + snes.cpu.S + 1) as u32)?;
-            let temp = u32::from_be_bytes([0, snes.cpu.DBR, high, low]);
-            temp + snes.cpu.Y as u32
+            let pointer = read_bank_pointer(0, cpu.S.wrapping_add(l as u16), false, fetch)?;
+            let (addr, page) = index_address(data_bank | pointer, cpu.Y);
+            crossed = page;
+            addr
         }
     };
-    Ok((addr, dest))
+    Ok((addr, dest, crossed))
 }
 
-pub fn decode_instruction(snes: &Console, instruction: u8, loc: u32) -> Result<InstructionContext> {
-    let opcode = match instruction {
-        0x00 => OpCode::BRK,
-        0x01 | 0x03 | 0x05 | 0x07 | 0x09 | 0x0D | 0x0F | 0x11 | 0x12 | 0x13 | 0x15 | 0x17
-        | 0x19 | 0x1D | 0x1F => OpCode::ORA,
-        0x02 => OpCode::COP,
-        0x04 | 0x0C => OpCode::TSB,
-        0x06 | 0x0A | 0x0E | 0x16 | 0x1E => OpCode::ASL,
-        0x08 => OpCode::PHP,
-        0x0B => OpCode::PHD,
-        0x10 => OpCode::BPL,
-        0x14 | 0x1C => OpCode::TRB,
-        0x18 => OpCode::CLC,
-        0x1A | 0xE6 | 0xEE | 0xF6 | 0xFE => OpCode::INC,
-        0x1B => OpCode::TCS,
-        0x20 | 0xFC => OpCode::JSR,
-        0x21 | 0x23 | 0x25 | 0x27 | 0x29 | 0x2D | 0x2F | 0x31 | 0x32 | 0x33 | 0x35 | 0x37
-        | 0x39 | 0x3D | 0x3F => OpCode::AND,
-        0x22 => OpCode::JSL,
-        0x24 | 0x2C | 0x34 | 0x3C | 0x89 => OpCode::BIT,
-        0x26 | 0x2A | 0x2E | 0x36 | 0x3E => OpCode::ROL,
-        0x28 => OpCode::PLP,
-        0x2B => OpCode::PLD,
-        0x30 => OpCode::BMI,
-        0x38 => OpCode::SEC,
-        0x3A | 0xC6 | 0xCE | 0xD6 | 0xDE => OpCode::DEC,
-        0x3B => OpCode::TSC,
-        0x40 => OpCode::RTI,
-        0x41 | 0x43 | 0x45 | 0x47 | 0x49 | 0x4D | 0x4F | 0x51 | 0x52 | 0x53 | 0x55 | 0x57
-        | 0x59 | 0x5D | 0x5F => OpCode::EOR,
-        0x42 => OpCode::WDM,
-        0x44 => OpCode::MVP,
-        0x46 | 0x4A | 0x4E | 0x56 | 0x5E => OpCode::LSR,
-        0x48 => OpCode::PHA,
-        0x4B => OpCode::PHK,
-        0x4C | 0x5C | 0x6C | 0x7C => OpCode::JMP,
-        0x50 => OpCode::BVC,
-        0x54 => OpCode::MVN,
-        0x58 => OpCode::CLI,
-        0x5A => OpCode::PHY,
-        0x5B => OpCode::TCD,
-        0x60 => OpCode::RTS,
-        0x61 | 0x63 | 0x65 | 0x67 | 0x69 | 0x6D | 0x6F | 0x71 | 0x72 | 0x73 | 0x75 | 0x77
-        | 0x79 | 0x7D | 0x7F => OpCode::ADC,
-        0x62 => OpCode::PER,
-        0x64 | 0x74 | 0x9C | 0x9E => OpCode::STZ,
-        0x66 | 0x6A | 0x6E | 0x76 | 0x7E => OpCode::ROR,
-        0x68 => OpCode::PLA,
-        0x6B => OpCode::RTL,
-        0x70 => OpCode::BVS,
-        0x78 => OpCode::SEI,
-        0x7A => OpCode::PLY,
-        0x7B => OpCode::TDC,
-        0x80 => OpCode::BRA,
-        0x81 | 0x83 | 0x85 | 0x87 | 0x8D | 0x8F | 0x91 | 0x92 | 0x93 | 0x95 | 0x97 | 0x99
-        | 0x9D | 0x9F => OpCode::STA,
-        0x82 => OpCode::BRL,
-        0x84 | 0x8C | 0x94 => OpCode::STY,
-        0x86 | 0x8E | 0x96 => OpCode::STX,
-        0x88 => OpCode::DEY,
-        0x8A => OpCode::TXA,
-        0x8B => OpCode::PHB,
-        0x90 => OpCode::BCC,
-        0x98 => OpCode::TYA,
-        0x9A => OpCode::TXS,
-        0x9B => OpCode::TXY,
-        0xA0 | 0xA4 | 0xAC | 0xB4 | 0xBC => OpCode::LDY,
-        0xA1 | 0xA3 | 0xA5 | 0xA7 | 0xA9 | 0xAD | 0xAF | 0xB1 | 0xB2 | 0xB3 | 0xB5 | 0xB7
-        | 0xB9 | 0xBD | 0xBF => OpCode::LDA,
-        0xA2 | 0xA6 | 0xAE | 0xB6 | 0xBE => OpCode::LDX,
-        0xA8 => OpCode::TAY,
-        0xAA => OpCode::TAX,
-        0xAB => OpCode::PLB,
-        0xB0 => OpCode::BCS,
-        0xB8 => OpCode::CLV,
-        0xBA => OpCode::TSX,
-        0xBB => OpCode::TYX,
-        0xC0 | 0xC4 | 0xCC => OpCode::CPY,
-        0xC1 | 0xC3 | 0xC5 | 0xC7 | 0xC9 | 0xCD | 0xCF | 0xD1 | 0xD2 | 0xD3 | 0xD5 | 0xD7
-        | 0xD9 | 0xDD | 0xDF => OpCode::CMP,
-        0xC2 => OpCode::REP,
-        0xC8 => OpCode::INY,
-        0xCA => OpCode::DEX,
-        0xCB => OpCode::WAI,
-        0xD0 => OpCode::BNE,
-        0xD4 => OpCode::PEI,
-        0xD8 => OpCode::CLD,
-        0xDA => OpCode::PHX,
-        0xDB => OpCode::STP,
-        0xDC => OpCode::JML,
-        0xE0 | 0xE4 | 0xEC => OpCode::CPX,
-        0xE1 | 0xE3 | 0xE5 | 0xE7 | 0xE9 | 0xED | 0xEF | 0xF1 | 0xF2 | 0xF3 | 0xF5 | 0xF7
-        | 0xF9 | 0xFD | 0xFF => OpCode::SBC,
-        0xE2 => OpCode::SEP,
-        0xE8 => OpCode::INX,
-        0xEA => OpCode::NOP,
-        0xEB => OpCode::XBA,
-        0xF0 => OpCode::BEQ,
-        0xF4 => OpCode::PEA,
-        0xF8 => OpCode::SED,
-        0xFA => OpCode::PLX,
-        0xFB => OpCode::XCE,
-    };
-    let mode = decode_addressing_mode(instruction)?;
-    let address = calculate_address(&snes, &opcode, &mode, loc)?;
+pub fn decode_instruction(
+    snes: &mut Console,
+    instruction: u8,
+    loc: u32,
+) -> Result<InstructionContext, CpuError> {
+    let cpu = snes.cpu.clone();
+    decode_with(&cpu, instruction, loc, &mut |addr| snes.read(addr))
+}
+
+/// Decode the instruction at `loc` with peeks, leaving the console untouched.
+/// Pointers are read from memory as it is now, so for tracing and lookahead
+/// rather than execution.
+pub fn peek_instruction(snes: &Console, loc: u32) -> Result<InstructionContext, CpuError> {
+    let instruction = snes
+        .peek(loc)
+        .map_err(|source| CpuError::Fetch { pc: loc, source })?;
+    decode_with(&snes.cpu, instruction, loc, &mut |addr| snes.peek(addr))
+}
+
+fn decode_with(
+    cpu: &CPU,
+    instruction: u8,
+    loc: u32,
+    fetch: &mut impl Fetch,
+) -> Result<InstructionContext, CpuError> {
+    let info = &OPCODES[instruction as usize];
+    let address = calculate_address(cpu, info, loc, fetch).map_err(|source| CpuError::Memory {
+        pc: loc,
+        opcode: info.opcode.clone(),
+        mode: info.mode.clone(),
+        source,
+    })?;
 
     Ok(InstructionContext {
-        opcode,
-        mode,
+        byte: instruction,
+        opcode: info.opcode.clone(),
+        mode: info.mode.clone(),
         inst_addr: loc,
         data_addr: address.0,
         dest_addr: address.1,
+        crossed_page: address.2,
     })
 }
 
+/// Read the operand of an instruction at the given width. Accumulator mode
+/// instructions operate on A itself.
+fn read_operand(snes: &mut Console, instruction: &InstructionContext, width: Width) -> Result<u16> {
+    if instruction.mode == AddrMode::Accumulator {
+        return Ok(snes.cpu.A & width.mask());
+    }
+    match width {
+        Width::Byte => Ok(snes.read(instruction.data_addr)? as u16),
+        Width::Word => snes.read_word(instruction.data_addr),
+    }
+}
+
+/// Write back the result of a read-modify-write instruction
+fn write_operand(
+    snes: &mut Console,
+    instruction: &InstructionContext,
+    width: Width,
+    value: u16,
+) -> Result<()> {
+    if instruction.mode == AddrMode::Accumulator {
+        snes.cpu.A = width.merge(snes.cpu.A, value);
+        return Ok(());
+    }
+    match width {
+        Width::Byte => snes.write(instruction.data_addr, value as u8),
+        Width::Word => snes.write_word(instruction.data_addr, value),
+    }
+}
+
+/// Binary ADC/SBC. SBC is an ADC of the inverted operand. Returns the result,
+/// carry and overflow.
+fn binary_add(a: u16, data: u16, carry: bool, width: Width, subtract: bool) -> (u16, bool, bool) {
+    let mask = width.mask();
+    let data = if subtract { !data & mask } else { data & mask };
+    let sum = (a & mask) as u32 + data as u32 + carry as u32;
+    let result = sum as u16 & mask;
+    let v = !(a ^ data) & (a ^ result) & width.sign() != 0;
+    (result, sum > mask as u32, v)
+}
+
+/// Decimal mode ADC/SBC, worked a nibble at a time like the real chip so that
+/// invalid BCD operands produce the same results. SBC is an ADC of the inverted
+/// operand with the adjustment flipped. Returns the result, carry and overflow.
+fn bcd_add(a: u16, data: u16, carry: bool, width: Width, subtract: bool) -> (u16, bool, bool) {
+    let nibbles = match width {
+        Width::Byte => 2,
+        Width::Word => 4,
+    };
+    let a = a as i32;
+    let data = if subtract { !data } else { data } as i32;
+    let mut result: i32 = 0;
+    let mut c = carry as i32;
+    let mut v = false;
+    for i in 0..nibbles {
+        let shift = i * 4;
+        let low = (1 << shift) - 1;
+        let nibble = 0xF << shift;
+        result = (a & nibble) + (data & nibble) + (c << shift) + (result & low);
+        if i == nibbles - 1 {
+            // Overflow is taken before the top nibble is adjusted
+            let sign = 1 << (shift + 3);
+            v = !(a ^ data) & (a ^ result) & sign != 0;
+        }
+        if subtract {
+            if result < 0x10 << shift {
+                result -= 0x6 << shift;
+            }
+        } else if result >= 0xA << shift {
+            result += 0x6 << shift;
+        }
+        c = (result >= 0x10 << shift) as i32;
+    }
+    ((result & width.mask() as i32) as u16, c != 0, v)
+}
+
 fn push_byte(snes: &mut Console, data: u8) -> Result<()> {
     match snes.cpu.P.e {
         true => {
             let mut sl = (snes.cpu.S & 0xFF) as u8;
-            memory::write_byte(snes, 0x000100 | sl as u32, data)?;
+            snes.write(0x000100 | sl as u32, data)?;
             sl = sl.wrapping_sub(1);
             snes.cpu.S &= 0xFF00;
             snes.cpu.S |= sl as u16;
         }
         false => {
-            memory::write_byte(snes, snes.cpu.S as u32, data)?;
+            snes.write(snes.cpu.S as u32, data)?;
             snes.cpu.S = snes.cpu.S.wrapping_sub(1);
         }
     }
@@ -1314,11 +1193,11 @@ fn pull_byte(snes: &mut Console) -> Result<u8> {
             sl = sl.wrapping_add(1);
             snes.cpu.S &= 0xFF00;
             snes.cpu.S |= sl as u16;
-            memory::read_byte(snes, 0x000100 | sl as u32)
+            snes.read(0x000100 | sl as u32)
         }
         false => {
             snes.cpu.S = snes.cpu.S.wrapping_add(1);
-            memory::read_byte(snes, snes.cpu.S as u32)
+            snes.read(snes.cpu.S as u32)
         }
     }
 }
@@ -1329,17 +1208,17 @@ fn push_word(snes: &mut Console, data: u16) -> Result<()> {
     match snes.cpu.P.e {
         true => {
             let mut sl = (snes.cpu.S & 0xFF) as u8;
-            memory::write_byte(snes, 0x000100 | sl as u32, datah)?;
+            snes.write(0x000100 | sl as u32, datah)?;
             sl = sl.wrapping_sub(1);
-            memory::write_byte(snes, 0x000100 | sl as u32, datal)?;
+            snes.write(0x000100 | sl as u32, datal)?;
             sl = sl.wrapping_sub(1);
             snes.cpu.S &= 0xFF00;
             snes.cpu.S |= sl as u16;
         }
         false => {
-            memory::write_byte(snes, snes.cpu.S as u32, datah)?;
+            snes.write(snes.cpu.S as u32, datah)?;
             snes.cpu.S = snes.cpu.S.wrapping_sub(1);
-            memory::write_byte(snes, snes.cpu.S as u32, datal)?;
+            snes.write(snes.cpu.S as u32, datal)?;
             snes.cpu.S = snes.cpu.S.wrapping_sub(1);
         }
     }
@@ -1351,18 +1230,18 @@ fn pull_word(snes: &mut Console) -> Result<u16> {
         true => {
             let mut sl = (snes.cpu.S & 0xFF) as u8;
             sl = sl.wrapping_add(1);
-            let datal = memory::read_byte(snes, 0x000100 | sl as u32)?;
+            let datal = snes.read(0x000100 | sl as u32)?;
             sl = sl.wrapping_add(1);
-            let datah = memory::read_byte(snes, 0x000100 | sl as u32)?;
+            let datah = snes.read(0x000100 | sl as u32)?;
             snes.cpu.S &= 0xFF00;
             snes.cpu.S |= sl as u16;
             Ok(u16::from_be_bytes([datah, datal]))
         }
         false => {
             snes.cpu.S = snes.cpu.S.wrapping_add(1);
-            let datal = memory::read_byte(snes, snes.cpu.S as u32)?;
+            let datal = snes.read(snes.cpu.S as u32)?;
             snes.cpu.S = snes.cpu.S.wrapping_add(1);
-            let datah = memory::read_byte(snes, snes.cpu.S as u32)?;
+            let datah = snes.read(snes.cpu.S as u32)?;
             Ok(u16::from_be_bytes([datah, datal]))
         }
     }
@@ -1370,87 +1249,243 @@ fn pull_word(snes: &mut Console) -> Result<u16> {
 
 fn push_sword(snes: &mut Console, data: u32) -> Result<()> {
     let databytes = data.to_le_bytes();
-    memory::write_byte(snes, snes.cpu.S as u32, databytes[1])?;
+    snes.write(snes.cpu.S as u32, databytes[1])?;
     snes.cpu.S = snes.cpu.S.wrapping_sub(1);
-    memory::write_byte(snes, snes.cpu.S as u32, databytes[2])?;
+    snes.write(snes.cpu.S as u32, databytes[2])?;
     snes.cpu.S = snes.cpu.S.wrapping_sub(1);
-    memory::write_byte(snes, snes.cpu.S as u32, databytes[3])?;
+    snes.write(snes.cpu.S as u32, databytes[3])?;
     snes.cpu.S = snes.cpu.S.wrapping_sub(1);
     Ok(())
 }
 
 fn pull_sword(snes: &mut Console) -> Result<u32> {
     snes.cpu.S = snes.cpu.S.wrapping_add(1);
-    let datal = memory::read_byte(snes, snes.cpu.S as u32)?;
+    let datal = snes.read(snes.cpu.S as u32)?;
     snes.cpu.S = snes.cpu.S.wrapping_add(1);
-    let datam = memory::read_byte(snes, snes.cpu.S as u32)?;
+    let datam = snes.read(snes.cpu.S as u32)?;
     snes.cpu.S = snes.cpu.S.wrapping_add(1);
-    let datah = memory::read_byte(snes, snes.cpu.S as u32)?;
+    let datah = snes.read(snes.cpu.S as u32)?;
     Ok(u32::from_be_bytes([0x00, datah, datam, datal]))
 }
 
-pub fn execute_nmi(snes: &mut Console) -> Result<()> {
-    push_byte(snes, snes.cpu.K)?;
-    push_word(snes, snes.cpu.PC)?;
-    push_byte(snes, snes.cpu.p_byte())?;
-    snes.cpu.PC = snes.cartridge.header.interrupt_vectors.nmi;
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    NMI,
+    IRQ,
+    ABORT,
+    BRK,
+    COP,
+}
+
+/// Enter an interrupt handler. Native mode pushes K, PC and P. Emulation mode
+/// pushes only PC and P, with B set in the pushed P for BRK. Both modes set I,
+/// clear D and jump through the matching vector in bank 0.
+pub fn interrupt(snes: &mut Console, kind: Interrupt, return_pc: u16) -> Result<()> {
+    let vectors = &snes.cartridge.header.interrupt_vectors;
+    let vector = match (kind, snes.cpu.P.e) {
+        (Interrupt::NMI, false) => vectors.nmi,
+        (Interrupt::IRQ, false) => vectors.irq,
+        (Interrupt::ABORT, false) => vectors.abort,
+        (Interrupt::BRK, false) => vectors.brk,
+        (Interrupt::COP, false) => vectors.cop,
+        (Interrupt::NMI, true) => vectors.nmi_emu,
+        // BRK shares the IRQ vector in emulation mode
+        (Interrupt::IRQ | Interrupt::BRK, true) => vectors.irq_emu,
+        (Interrupt::ABORT, true) => vectors.abort_emu,
+        (Interrupt::COP, true) => vectors.cop_emu,
+    };
+    trace!("{:?} from ${:02X}{:04X}", kind, snes.cpu.K, return_pc);
+    snes.hooks.interrupt(snes, kind);
+    if snes.cpu.P.e {
+        push_word(snes, return_pc)?;
+        let b = if kind == Interrupt::BRK {
+            0b00010000
+        } else {
+            0
+        };
+        push_byte(snes, snes.cpu.p_byte() | b)?;
+    } else {
+        push_byte(snes, snes.cpu.K)?;
+        push_word(snes, return_pc)?;
+        push_byte(snes, snes.cpu.p_byte())?;
+    }
+    snes.cpu.P.i = true;
+    snes.cpu.P.d = false;
     snes.cpu.K = 0;
+    snes.cpu.PC = vector;
     Ok(())
 }
 
+/// Take a pending NMI, or a pending IRQ when I is clear. Returns the cycles
+/// spent entering the handler.
+fn poll_interrupts(snes: &mut Console) -> Result<Option<u8>, CpuError> {
+    let kind = if snes.cpu.nmi_pending {
+        snes.cpu.nmi_pending = false;
+        Interrupt::NMI
+    } else if snes.cpu.irq_pending && !snes.cpu.P.i {
+        Interrupt::IRQ
+    } else {
+        return Ok(None);
+    };
+    let cycles = 8 - snes.cpu.P.e as u8;
+    let stack_bytes = cycles as u64 - 4;
+    let stack = if snes.cpu.P.e {
+        0x0100 | (snes.cpu.S & 0xFF) as u32
+    } else {
+        snes.cpu.S as u32
+    };
+    // Two internal cycles, the frame pushes and the vector fetch
+    let master_cycles = 2 * 6
+        + stack_bytes * memory::access_speed(snes, stack) as u64
+        + 2 * memory::access_speed(snes, 0x00FFE0) as u64;
+    let start = Timestamp::now(snes);
+    interrupt(snes, kind, snes.cpu.PC).map_err(|source| CpuError::Interrupt {
+        pc: snes.cpu.get_pc(),
+        kind,
+        source,
+    })?;
+    start.settle(snes, cycles, master_cycles);
+    Ok(Some(cycles))
+}
+
 fn execute_instruction_emu(
     snes: &mut Console,
     instruction: &InstructionContext,
 ) -> Result<CPUExecutionResult> {
-    trace!("Executing {} in emu mode", instruction.opcode);
+    trace!("Executing {} in emu mode", instruction.with_source(snes));
     match instruction.opcode {
-        OpCode::BRK => {
-            push_word(snes, (instruction.inst_addr & 0xFF) as u16 + 2)?;
-            let p = snes.cpu.p_byte() | 0b00010000;
-            push_byte(snes, p)?;
-            snes.cpu.K = 0;
-            snes.cpu.PC = snes.cartridge.header.interrupt_vectors.brk_emu;
-            snes.cpu.P.i = true;
-            snes.cpu.P.d = false;
-            return Ok(CPUExecutionResult::Interrupt);
+        OpCode::RTI => {
+            let p = pull_byte(snes)?;
+            snes.cpu.set_p(p);
+            snes.cpu.PC = pull_word(snes)?;
+            return Ok(CPUExecutionResult::Return);
         }
-        OpCode::JMP => {
-            snes.cpu.set_pc(instruction.data_addr);
-            return Ok(CPUExecutionResult::Jump);
+        OpCode::PHP => {
+            push_byte(snes, snes.cpu.p_byte() | 0b00010000)?;
         }
-        OpCode::SEI => {
-            snes.cpu.P.i = true;
+        OpCode::TCS => {
+            snes.cpu.S = 0x0100 | (snes.cpu.A & 0xFF);
         }
-        OpCode::CLC => {
-            snes.cpu.P.c = false;
+        OpCode::TXS => {
+            snes.cpu.S = 0x0100 | (snes.cpu.X & 0xFF);
         }
-        OpCode::XCE => {
-            let temp = snes.cpu.P.c;
-            snes.cpu.P.c = snes.cpu.P.e;
-            snes.cpu.P.e = temp;
+        _ => {
+            let result = execute_instruction_native(snes, instruction)?;
+            // REP can't widen the registers while E is still set
+            if snes.cpu.P.e {
+                snes.cpu.P.m = true;
+                snes.cpu.P.x = true;
+            }
+            return Ok(result);
         }
-        _ => todo!(
-            "Implement remaining instructions, tried to execute: {} in emu mode",
-            instruction.opcode
-        ),
     }
-    snes.cpu.PC += instruction.length(snes.cpu.P.m, snes.cpu.P.x) as u16;
+    // PC wraps within the program bank
+    snes.cpu.PC = snes
+        .cpu
+        .PC
+        .wrapping_add(instruction.length(snes.cpu.P.m, snes.cpu.P.x) as u16);
     Ok(CPUExecutionResult::Normal)
 }
 
+/// Master clock and CPU cycle count when an instruction started
+#[derive(Debug, Clone, Copy)]
+struct Timestamp {
+    clock: u64,
+    cycles: u64,
+}
+
+impl Timestamp {
+    fn now(snes: &Console) -> Timestamp {
+        Timestamp {
+            clock: snes.master_clock,
+            cycles: snes.cpu.cycles,
+        }
+    }
+
+    /// Bus accesses advance both counts while an instruction runs. Once it's
+    /// done they're set to its full timing, which adds the internal cycles.
+    fn settle(self, snes: &mut Console, cycles: u8, master_cycles: u64) {
+        snes.master_clock = self.clock + master_cycles;
+        snes.cpu.cycles = self.cycles + cycles as u64;
+    }
+}
+
+/// Execute a decoded instruction and advance the master clock. Returns the
+/// result along with the number of CPU cycles the instruction took. Timing
+/// is counted from here, so reads made decoding the instruction beforehand
+/// are charged on top; `step` counts from the opcode fetch instead.
 pub fn execute_instruction(
     snes: &mut Console,
     instruction: &InstructionContext,
-) -> Result<CPUExecutionResult> {
-    if snes.cpu.P.e {
-        return execute_instruction_emu(snes, &instruction);
+) -> Result<(CPUExecutionResult, u8), CpuError> {
+    execute_from(snes, instruction, Timestamp::now(snes))
+}
+
+/// Execute an instruction whose opcode fetch started at `start`
+fn execute_from(
+    snes: &mut Console,
+    instruction: &InstructionContext,
+    start: Timestamp,
+) -> Result<(CPUExecutionResult, u8), CpuError> {
+    let cycles = calculate_cycles(snes, instruction);
+    let master_cycles = calculate_master_cycles(snes, instruction, cycles);
+    snes.hooks.pre_execute(snes, instruction);
+    let result = if snes.cpu.P.e {
+        execute_instruction_emu(snes, instruction)
+    } else {
+        trace!("Executing {}", instruction.with_source(snes));
+        execute_instruction_native(snes, instruction)
+    }
+    .map_err(|source| CpuError::Memory {
+        pc: instruction.inst_addr,
+        opcode: instruction.opcode.clone(),
+        mode: instruction.mode.clone(),
+        source,
+    })?;
+    start.settle(snes, cycles, master_cycles);
+    snes.hooks.post_execute(snes, instruction);
+    Ok((result, cycles))
+}
+
+/// Fetch, decode and execute the instruction at PC. Pending interrupts are
+/// taken first, in which case no instruction is executed. A waiting or stopped
+/// CPU idles for one cycle instead.
+pub fn step(snes: &mut Console) -> Result<(CPUExecutionResult, u8), CpuError> {
+    // Any interrupt line wakes WAI, even one that I then masks
+    if snes.cpu.state == RunState::Waiting && (snes.cpu.nmi_pending || snes.cpu.irq_pending) {
+        snes.cpu.state = RunState::Running;
+    }
+    if snes.cpu.state != RunState::Running {
+        snes.master_clock += 6;
+        snes.cpu.cycles += 1;
+        return Ok((CPUExecutionResult::Idle, 1));
     }
-    trace!("Executing {}", instruction.with_source(snes));
+    if let Some(cycles) = poll_interrupts(snes)? {
+        return Ok((CPUExecutionResult::Interrupt, cycles));
+    }
+    let start = Timestamp::now(snes);
+    let pc = snes.cpu.get_pc();
+    let op = snes
+        .read(pc)
+        .map_err(|source| CpuError::Fetch { pc, source })?;
+    let instruction = decode_instruction(snes, op, pc)?;
+    execute_from(snes, &instruction, start)
+}
+
+fn execute_instruction_native(
+    snes: &mut Console,
+    instruction: &InstructionContext,
+) -> Result<CPUExecutionResult> {
     match instruction.opcode {
         OpCode::JMP => {
             snes.cpu.set_pc(instruction.data_addr);
             return Ok(CPUExecutionResult::Jump);
         }
+        OpCode::JML => {
+            snes.cpu.set_pc(instruction.data_addr);
+            return Ok(CPUExecutionResult::Jump);
+        }
         OpCode::JSL => {
             push_byte(snes, snes.cpu.K)?;
             push_word(snes, (instruction.inst_addr as u16).wrapping_add(3))?;
@@ -1469,460 +1504,166 @@ pub fn execute_instruction(
             snes.cpu.K = pull_byte(snes)?;
             return Ok(CPUExecutionResult::Return);
         }
-        OpCode::ADC => {
-            if snes.cpu.P.d {
-                unimplemented!("Decimal mode");
-                // let mut temp_A: [u8; 4] = [0;4];
-                // temp_A[0] = (snes.cpu.A & 0x000F) as u8;
-                // temp_A[1] = ((snes.cpu.A & 0x00F0) >> 4) as u8;
-                // temp_A[2] = ((snes.cpu.A & 0x0F00) >> 8) as u8;
-                // temp_A[3] = ((snes.cpu.A & 0xF000) >> 12) as u8;
-                // let mut temp_val: [u8; 4] = [0;4];
-                // let temp = memory::read_word(snes, instruction.data_addr)?;
-                // temp_val[0] = (temp & 0x000F) as u8;
-                // temp_val[1] = ((temp & 0x00F0) >> 4) as u8;
-                // temp_val[2] = ((temp & 0x0F00) >> 8) as u8;
-                // temp_val[3] = ((temp & 0xF000) >> 12) as u8;
-            } else {
-                let overflow: bool;
-                let overflow2: bool;
-                (snes.cpu.A, overflow) = snes
-                    .cpu
-                    .A
-                    .overflowing_add(memory::read_word(snes, instruction.data_addr)?);
-                (snes.cpu.A, overflow2) = snes.cpu.A.overflowing_add(snes.cpu.P.c as u16);
-                snes.cpu.P.z = snes.cpu.A == 0;
-                snes.cpu.P.n = snes.cpu.A >= 0b10000000;
-                snes.cpu.P.v = overflow || overflow2;
-                snes.cpu.P.c = overflow || overflow2;
-            }
-        }
-        OpCode::AND => {
-            if snes.cpu.P.m {
-                let temp =
-                    (snes.cpu.A & 0xFF) as u8 & memory::read_byte(snes, instruction.data_addr)?;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.n = temp & 0x80 != 0;
-                snes.cpu.A &= 0xFF00;
-                snes.cpu.A |= temp as u16;
-            } else {
-                let temp = snes.cpu.A & memory::read_word(snes, instruction.data_addr)?;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.n = temp & 0x8000 != 0;
-                snes.cpu.A = temp;
-            }
-        }
-        OpCode::ASL => {
-            if snes.cpu.P.m {
-                if instruction.mode == AddrMode::Accumulator {
-                    let temp = snes.cpu.A << 1;
-                    snes.cpu.P.c = (snes.cpu.A & 0x80) > 0;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = temp >= 0b10000000;
-                    snes.cpu.A = temp;
-                } else {
-                    let data = memory::read_byte(snes, instruction.data_addr)?;
-                    let temp = data << 1;
-                    snes.cpu.P.c = (data & 0x80) > 0;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = temp >= 0b10000000;
-                    memory::write_byte(snes, instruction.data_addr, temp)?;
-                }
+        OpCode::ADC | OpCode::SBC => {
+            let width = snes.cpu.m_width();
+            let data = read_operand(snes, instruction, width)?;
+            let subtract = instruction.opcode == OpCode::SBC;
+            let (result, c, v) = if snes.cpu.P.d {
+                bcd_add(snes.cpu.A, data, snes.cpu.P.c, width, subtract)
             } else {
-                if instruction.mode == AddrMode::Accumulator {
-                    let temp = snes.cpu.A << 1;
-                    snes.cpu.P.c = (snes.cpu.A & 0x8000) > 0;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = temp >= 0b10000000;
-                    snes.cpu.A = temp;
-                } else {
-                    let data = memory::read_word(snes, instruction.data_addr)?;
-                    let temp = data << 1;
-                    snes.cpu.P.c = (data & 0x8000) > 0;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = temp >= 0b10000000;
-                    memory::write_word(snes, instruction.data_addr, temp)?;
-                }
-            }
-        }
-        OpCode::BCC => {
-            if !snes.cpu.P.c {
-                snes.cpu.PC = instruction.data_addr as u16;
-                return Ok(CPUExecutionResult::BranchTaken);
-            }
-        }
-        OpCode::BEQ => {
-            if snes.cpu.P.z {
+                binary_add(snes.cpu.A, data, snes.cpu.P.c, width, subtract)
+            };
+            snes.cpu.P.c = c;
+            snes.cpu.P.v = v;
+            snes.cpu.set_a(result);
+        }
+        OpCode::AND | OpCode::EOR | OpCode::ORA => {
+            let data = read_operand(snes, instruction, snes.cpu.m_width())?;
+            let result = match instruction.opcode {
+                OpCode::AND => snes.cpu.A & data,
+                OpCode::EOR => snes.cpu.A ^ data,
+                _ => snes.cpu.A | data,
+            };
+            snes.cpu.set_a(result);
+        }
+        OpCode::ASL | OpCode::LSR | OpCode::ROL | OpCode::ROR => {
+            let width = snes.cpu.m_width();
+            let data = read_operand(snes, instruction, width)?;
+            let carry_in = snes.cpu.P.c as u16;
+            let (result, carry) = match instruction.opcode {
+                OpCode::ASL => (data << 1, data & width.sign() != 0),
+                OpCode::ROL => ((data << 1) | carry_in, data & width.sign() != 0),
+                OpCode::LSR => (data >> 1, data & 1 != 0),
+                _ => ((data >> 1) | (carry_in * width.sign()), data & 1 != 0),
+            };
+            let result = result & width.mask();
+            write_operand(snes, instruction, width, result)?;
+            snes.cpu.P.c = carry;
+            snes.cpu.set_nz(result, width);
+        }
+        OpCode::BCC
+        | OpCode::BCS
+        | OpCode::BEQ
+        | OpCode::BNE
+        | OpCode::BMI
+        | OpCode::BPL
+        | OpCode::BVC
+        | OpCode::BVS
+        | OpCode::BRA
+        | OpCode::BRL => {
+            if branch_condition(&snes.cpu, &instruction.opcode) {
                 snes.cpu.PC = instruction.data_addr as u16;
                 return Ok(CPUExecutionResult::BranchTaken);
             }
         }
-        OpCode::BRA => {
-            snes.cpu.PC = instruction.data_addr as u16;
-            return Ok(CPUExecutionResult::BranchTaken);
-        }
         OpCode::BIT => {
+            let width = snes.cpu.m_width();
+            let data = read_operand(snes, instruction, width)?;
+            snes.cpu.P.z = snes.cpu.A & data & width.mask() == 0;
+            // Immediate BIT only affects Z
             if instruction.mode != AddrMode::Immediate {
-                snes.cpu.P.z = snes.cpu.A.to_be_bytes()[1]
-                    & memory::read_byte(snes, instruction.data_addr)?
-                    == 0;
-            } else {
-                if snes.cpu.P.m {
-                    let temp = memory::read_byte(snes, instruction.data_addr)?;
-                    snes.cpu.P.z = temp & snes.cpu.A.to_be_bytes()[1] == 0;
-                    snes.cpu.P.n = temp & 0x80 > 0;
-                    snes.cpu.P.v = temp & 0x40 > 0;
-                } else {
-                    let temp = memory::read_word(snes, instruction.data_addr)?;
-                    snes.cpu.P.z = temp & snes.cpu.A == 0;
-                    snes.cpu.P.n = temp & 0x8000 > 0;
-                    snes.cpu.P.v = temp & 0x4000 > 0;
-                }
-            }
-        }
-        OpCode::BPL => {
-            if !snes.cpu.P.n {
-                snes.cpu.PC = instruction.data_addr as u16;
-                return Ok(CPUExecutionResult::BranchTaken);
-            }
-        }
-        OpCode::BMI => {
-            if snes.cpu.P.n {
-                snes.cpu.PC = instruction.data_addr as u16;
-                return Ok(CPUExecutionResult::BranchTaken);
-            }
-        }
-        OpCode::BNE => {
-            if !snes.cpu.P.z {
-                snes.cpu.PC = instruction.data_addr as u16;
-                return Ok(CPUExecutionResult::BranchTaken);
+                snes.cpu.P.n = data & width.sign() != 0;
+                snes.cpu.P.v = data & (width.sign() >> 1) != 0;
             }
         }
-        OpCode::BRK => {
-            push_byte(snes, snes.cpu.K)?;
-            push_word(snes, (instruction.inst_addr & 0xFF) as u16 + 2)?;
-            push_byte(snes, snes.cpu.p_byte())?;
-            snes.cpu.K = 0;
-            snes.cpu.PC = snes.cartridge.header.interrupt_vectors.brk;
+        OpCode::BRK | OpCode::COP => {
+            let kind = if instruction.opcode == OpCode::BRK {
+                Interrupt::BRK
+            } else {
+                Interrupt::COP
+            };
+            interrupt(snes, kind, (instruction.inst_addr as u16).wrapping_add(2))?;
             return Ok(CPUExecutionResult::Interrupt);
         }
         OpCode::CLC => {
             snes.cpu.P.c = false;
         }
+        OpCode::CLD => {
+            snes.cpu.P.d = false;
+        }
         OpCode::CLI => {
             snes.cpu.P.i = false;
         }
+        OpCode::CLV => {
+            snes.cpu.P.v = false;
+        }
         OpCode::SEC => {
             snes.cpu.P.c = true;
         }
+        OpCode::SED => {
+            snes.cpu.P.d = true;
+        }
+        OpCode::SEI => {
+            snes.cpu.P.i = true;
+        }
         OpCode::CMP => {
-            if snes.cpu.P.m {
-                let data = memory::read_byte(snes, instruction.data_addr)?;
-                let temp = u16::wrapping_sub(snes.cpu.A as u16, data as u16);
-                snes.cpu.P.n = temp >= 0b10000000;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.c = (snes.cpu.A as u16) >= (data as u16);
-            } else {
-                let data = memory::read_word(snes, instruction.data_addr)?;
-                let temp = u16::wrapping_sub(snes.cpu.A, data);
-                snes.cpu.P.n = temp >= 0b10000000;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.c = snes.cpu.A >= data;
-            }
+            let width = snes.cpu.m_width();
+            let data = read_operand(snes, instruction, width)?;
+            snes.cpu.compare(snes.cpu.A, data, width);
         }
         OpCode::CPX => {
-            if snes.cpu.P.x {
-                let data = memory::read_byte(snes, instruction.data_addr)?;
-                let temp = u16::wrapping_sub(snes.cpu.X as u16, data as u16);
-                snes.cpu.P.n = temp >= 0b10000000;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.c = (snes.cpu.X as u16) >= (data as u16);
-            } else {
-                let data = memory::read_word(snes, instruction.data_addr)?;
-                let temp = u16::wrapping_sub(snes.cpu.X, data);
-                snes.cpu.P.n = temp >= 0b10000000;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.c = snes.cpu.X >= data;
-            }
+            let width = snes.cpu.x_width();
+            let data = read_operand(snes, instruction, width)?;
+            snes.cpu.compare(snes.cpu.X, data, width);
         }
         OpCode::CPY => {
-            if snes.cpu.P.x {
-                let data = memory::read_byte(snes, instruction.data_addr)?;
-                let temp = u16::wrapping_sub(snes.cpu.Y as u16, data as u16);
-                snes.cpu.P.n = temp >= 0b10000000;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.c = (snes.cpu.Y as u16) >= (data as u16);
+            let width = snes.cpu.x_width();
+            let data = read_operand(snes, instruction, width)?;
+            snes.cpu.compare(snes.cpu.Y, data, width);
+        }
+        OpCode::DEC | OpCode::INC => {
+            let width = snes.cpu.m_width();
+            let data = read_operand(snes, instruction, width)?;
+            let result = if instruction.opcode == OpCode::INC {
+                data.wrapping_add(1)
             } else {
-                let data = memory::read_word(snes, instruction.data_addr)?;
-                let temp = u16::wrapping_sub(snes.cpu.Y, data);
-                snes.cpu.P.n = temp >= 0b10000000;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.P.c = snes.cpu.Y >= data;
-            }
-        }
-        OpCode::DEC => {
-            if instruction.mode == AddrMode::Accumulator {
-                if snes.cpu.P.m {
-                    let mut temp = (snes.cpu.A & 0xFF) as u8;
-                    temp = temp.wrapping_sub(1);
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= temp as u16;
-                    snes.cpu.P.n = (temp & 0x80) != 0;
-                    snes.cpu.P.z = temp == 0;
-                } else {
-                    snes.cpu.A = snes.cpu.A.wrapping_sub(1);
-                    snes.cpu.P.n = (snes.cpu.A & 0x8000) != 0;
-                    snes.cpu.P.z = snes.cpu.A == 0;
-                }
-            } else {
-                if snes.cpu.P.m {
-                    let mut data = memory::read_byte(snes, instruction.data_addr)?;
-                    data = data.wrapping_sub(1);
-                    memory::write_byte(snes, instruction.data_addr, data)?;
-                    snes.cpu.P.n = (data & 0x80) != 0;
-                    snes.cpu.P.z = data == 0;
-                } else {
-                    let mut data = memory::read_word(snes, instruction.data_addr)?;
-                    data = data.wrapping_sub(1);
-                    memory::write_word(snes, instruction.data_addr, data)?;
-                    snes.cpu.P.n = (data & 0x8000) != 0;
-                    snes.cpu.P.z = data == 0;
-                }
-            }
+                data.wrapping_sub(1)
+            } & width.mask();
+            write_operand(snes, instruction, width, result)?;
+            snes.cpu.set_nz(result, width);
         }
         OpCode::DEX => {
-            if snes.cpu.P.x {
-                let mut temp = (snes.cpu.X & 0xFF) as u8;
-                temp = temp.wrapping_sub(1);
-                snes.cpu.X &= 0xFF00;
-                snes.cpu.X |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.X = snes.cpu.X.wrapping_sub(1);
-                snes.cpu.P.n = (snes.cpu.X & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.X == 0;
-            }
+            snes.cpu.set_x(snes.cpu.X.wrapping_sub(1));
         }
         OpCode::DEY => {
-            if snes.cpu.P.x {
-                let mut temp = (snes.cpu.Y & 0xFF) as u8;
-                temp = temp.wrapping_sub(1);
-                snes.cpu.Y &= 0xFF00;
-                snes.cpu.Y |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.Y = snes.cpu.Y.wrapping_sub(1);
-                snes.cpu.P.n = (snes.cpu.Y & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.Y == 0;
-            }
-        }
-        OpCode::INC => {
-            if instruction.mode == AddrMode::Accumulator {
-                if snes.cpu.P.m {
-                    let mut temp = (snes.cpu.A & 0xFF) as u8;
-                    temp = temp.wrapping_add(1);
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= temp as u16;
-                    snes.cpu.P.n = (temp & 0x80) != 0;
-                    snes.cpu.P.z = temp == 0;
-                } else {
-                    snes.cpu.A = snes.cpu.A.wrapping_add(1);
-                    snes.cpu.P.n = (snes.cpu.A & 0x8000) != 0;
-                    snes.cpu.P.z = snes.cpu.A == 0;
-                }
-            } else {
-                if snes.cpu.P.m {
-                    let mut data = memory::read_byte(snes, instruction.data_addr)?;
-                    data = data.wrapping_add(1);
-                    memory::write_byte(snes, instruction.data_addr, data)?;
-                    snes.cpu.P.n = (data & 0x80) != 0;
-                    snes.cpu.P.z = data == 0;
-                } else {
-                    let mut data = memory::read_word(snes, instruction.data_addr)?;
-                    data = data.wrapping_add(1);
-                    memory::write_word(snes, instruction.data_addr, data)?;
-                    snes.cpu.P.n = (data & 0x8000) != 0;
-                    snes.cpu.P.z = data == 0;
-                }
-            }
+            snes.cpu.set_y(snes.cpu.Y.wrapping_sub(1));
         }
         OpCode::INX => {
-            if snes.cpu.P.x {
-                let mut temp = (snes.cpu.X & 0xFF) as u8;
-                temp = temp.wrapping_add(1);
-                snes.cpu.X &= 0xFF00;
-                snes.cpu.X |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.X = snes.cpu.X.wrapping_add(1);
-                snes.cpu.P.n = (snes.cpu.X & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.X == 0;
-            }
+            snes.cpu.set_x(snes.cpu.X.wrapping_add(1));
         }
         OpCode::INY => {
-            if snes.cpu.P.x {
-                let mut temp = (snes.cpu.Y & 0xFF) as u8;
-                temp = temp.wrapping_add(1);
-                snes.cpu.Y &= 0xFF00;
-                snes.cpu.Y |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.Y = snes.cpu.Y.wrapping_add(1);
-                snes.cpu.P.n = (snes.cpu.Y & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.Y == 0;
-            }
-        }
-        OpCode::LSR => {
-            if snes.cpu.P.m {
-                if instruction.mode == AddrMode::Accumulator {
-                    snes.cpu.P.c = (snes.cpu.A & 0x01) != 0;
-                    let mut temp_a = (snes.cpu.A & 0xFE) as u8;
-                    temp_a >>= 1;
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= temp_a as u16;
-                    snes.cpu.P.z = temp_a == 0;
-                    snes.cpu.P.n = false;
-                } else {
-                    let mut temp = read_byte(snes, instruction.data_addr)?;
-                    snes.cpu.P.c = (temp & 0x01) != 0;
-                    let temp = (temp & 0xFE) >> 1;
-                    write_byte(snes, instruction.data_addr, temp)?;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = false;
-                }
-            } else {
-                if instruction.mode == AddrMode::Accumulator {
-                    snes.cpu.P.c = (snes.cpu.A & 0x0001) != 0;
-                    let temp_a = (snes.cpu.A & 0xFFFE) >> 1;
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= temp_a;
-                    snes.cpu.P.z = temp_a == 0;
-                    snes.cpu.P.n = false;
-                } else {
-                    let mut temp = read_word(snes, instruction.data_addr)?;
-                    snes.cpu.P.c = (temp & 0x0001) != 0;
-                    let temp = (temp & 0xFFFE) >> 1;
-                    write_word(snes, instruction.data_addr, temp)?;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = false;
-                }
-            }
-        }
-        OpCode::ROL => {
-            if snes.cpu.P.m {
-                let temp_c;
-                if instruction.mode == AddrMode::Accumulator {
-                    let mut temp_a = (snes.cpu.A & 0xFF) as u8;
-                    temp_c = (snes.cpu.A & 0x00F0) as u8;
-                    temp_a = (temp_a & 0x7F) << 1;
-                    temp_a += snes.cpu.P.c as u8;
-                    snes.cpu.P.c = temp_c != 0;
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= temp_a as u16;
-                    snes.cpu.P.z = temp_a == 0;
-                    snes.cpu.P.n = (temp_a & 0x80) != 0;
-                } else {
-                    let mut temp = read_byte(snes, instruction.data_addr)?;
-                    temp_c = (temp & 0xF0) as u8;
-                    temp = (temp & 0x7F) << 1;
-                    temp += snes.cpu.P.c as u8;
-                    snes.cpu.P.c = temp_c != 0;
-                    write_byte(snes, instruction.data_addr, temp)?;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = (temp & 0x80) != 0;
-                }
-            } else {
-                let temp_c;
-                if instruction.mode == AddrMode::Accumulator {
-                    let mut temp_a = snes.cpu.A;
-                    temp_c = temp_a & 0xF000;
-                    temp_a = (temp_a & 0x7FFF) << 1;
-                    temp_a += snes.cpu.P.c as u16;
-                    snes.cpu.P.c = temp_c != 0;
-                    snes.cpu.A = temp_a;
-                    snes.cpu.P.z = temp_a != 0;
-                    snes.cpu.P.n = (temp_a & 0x8000) != 0;
-                } else {
-                    let mut temp = read_word(snes, instruction.data_addr)?;
-                    temp_c = temp & 0xF000;
-                    temp = (temp & 0x7FFF) << 1;
-                    temp += snes.cpu.P.c as u16;
-                    snes.cpu.P.c = temp_c != 0;
-                    write_word(snes, instruction.data_addr, temp)?;
-                    snes.cpu.P.z = temp != 0;
-                    snes.cpu.P.n = (temp & 0x8000) != 0;
-                }
-            }
-        }
-        OpCode::ROR => {
-            if snes.cpu.P.m {
-                if instruction.mode == AddrMode::Accumulator {
-                    snes.cpu.P.c = (snes.cpu.A & 0x01) != 0;
-                    let temp_a = (snes.cpu.A & 0xFE) >> 1;
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= temp_a as u16;
-                    snes.cpu.P.z = temp_a == 0;
-                    snes.cpu.P.n = (temp_a & 0x80) != 0;
-                } else {
-                    let temp = memory::read_byte(snes, instruction.data_addr)?;
-                    snes.cpu.P.c = (temp & 0x01) != 0;
-                    let temp = (temp & 0xFE) >> 1;
-                    memory::write_byte(snes, instruction.data_addr, temp)?;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = (temp & 0x80) != 0;
-                }
-            } else {
-                if instruction.mode == AddrMode::Accumulator {
-                    snes.cpu.P.c = (snes.cpu.A & 0x0001) != 0;
-                    let temp_a = (snes.cpu.A & 0xFFFE) >> 1;
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= temp_a;
-                    snes.cpu.P.z = temp_a == 0;
-                    snes.cpu.P.n = (temp_a & 0x8000) != 0;
-                } else {
-                    let temp = memory::read_word(snes, instruction.data_addr)?;
-                    snes.cpu.P.c = (temp & 0x0001) != 0;
-                    let temp = (temp & 0xFFFE) >> 1;
-                    memory::write_word(snes, instruction.data_addr, temp)?;
-                    snes.cpu.P.z = temp == 0;
-                    snes.cpu.P.n = (temp & 0x8000) != 0;
-                }
-            }
+            snes.cpu.set_y(snes.cpu.Y.wrapping_add(1));
         }
         OpCode::STA => {
             if snes.cpu.P.m {
-                memory::write_byte(snes, instruction.data_addr, snes.cpu.A.to_le_bytes()[0])?;
+                snes.write(instruction.data_addr, snes.cpu.A.to_le_bytes()[0])?;
             } else {
-                memory::write_word(snes, instruction.data_addr, snes.cpu.A)?;
+                snes.write_word(instruction.data_addr, snes.cpu.A)?;
             }
         }
         OpCode::STX => {
             if snes.cpu.P.x {
-                memory::write_byte(snes, instruction.data_addr, snes.cpu.X.to_le_bytes()[0])?;
+                snes.write(instruction.data_addr, snes.cpu.X.to_le_bytes()[0])?;
             } else {
-                memory::write_word(snes, instruction.data_addr, snes.cpu.X)?;
+                snes.write_word(instruction.data_addr, snes.cpu.X)?;
             }
         }
         OpCode::STY => {
             if snes.cpu.P.x {
-                memory::write_byte(snes, instruction.data_addr, snes.cpu.Y.to_le_bytes()[0])?;
+                snes.write(instruction.data_addr, snes.cpu.Y.to_le_bytes()[0])?;
             } else {
-                memory::write_word(snes, instruction.data_addr, snes.cpu.Y)?;
+                snes.write_word(instruction.data_addr, snes.cpu.Y)?;
             }
         }
         OpCode::STZ => {
             if snes.cpu.P.m {
-                memory::write_byte(snes, instruction.data_addr, 0)?;
+                snes.write(instruction.data_addr, 0)?;
             } else {
-                memory::write_word(snes, instruction.data_addr, 0)?;
+                snes.write_word(instruction.data_addr, 0)?;
             }
         }
         OpCode::REP => {
-            let flags = memory::read_byte(snes, instruction.data_addr)?;
+            let flags = snes.read(instruction.data_addr)?;
             if flags & 0b1 != 0 {
                 snes.cpu.P.c = false;
             }
@@ -1949,7 +1690,7 @@ pub fn execute_instruction(
             }
         }
         OpCode::SEP => {
-            let flags = memory::read_byte(snes, instruction.data_addr)?;
+            let flags = snes.read(instruction.data_addr)?;
             if flags & 0b1 != 0 {
                 snes.cpu.P.c = true;
             }
@@ -1964,6 +1705,8 @@ pub fn execute_instruction(
             }
             if flags & 0b10000 != 0 {
                 snes.cpu.P.x = true;
+                snes.cpu.X &= 0x00FF;
+                snes.cpu.Y &= 0x00FF;
             }
             if flags & 0b100000 != 0 {
                 snes.cpu.P.m = true;
@@ -1975,47 +1718,30 @@ pub fn execute_instruction(
                 snes.cpu.P.n = true;
             }
         }
-        OpCode::ORA => {
-            snes.cpu.A |= memory::read_word(snes, instruction.data_addr)?;
-        }
         OpCode::LDA => {
-            if snes.cpu.P.m {
-                snes.cpu.A &= 0xFF00;
-                let temp = memory::read_byte(snes, instruction.data_addr)?;
-                snes.cpu.A |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.A = memory::read_word(snes, instruction.data_addr)?;
-                snes.cpu.P.n = (snes.cpu.A & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.A == 0;
-            }
+            let data = read_operand(snes, instruction, snes.cpu.m_width())?;
+            snes.cpu.set_a(data);
         }
         OpCode::LDX => {
-            if snes.cpu.P.x {
-                snes.cpu.X &= 0xFF00;
-                let temp = memory::read_byte(snes, instruction.data_addr)?;
-                snes.cpu.X |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.X = memory::read_word(snes, instruction.data_addr)?;
-                snes.cpu.P.n = (snes.cpu.X & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.X == 0;
-            }
+            let data = read_operand(snes, instruction, snes.cpu.x_width())?;
+            snes.cpu.set_x(data);
         }
         OpCode::LDY => {
-            if snes.cpu.P.x {
-                snes.cpu.Y &= 0xFF00;
-                let temp = memory::read_byte(snes, instruction.data_addr)?;
-                snes.cpu.Y |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.Y = memory::read_word(snes, instruction.data_addr)?;
-                snes.cpu.P.n = (snes.cpu.Y & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.Y == 0;
-            }
+            let data = read_operand(snes, instruction, snes.cpu.x_width())?;
+            snes.cpu.set_y(data);
+        }
+        OpCode::PEA | OpCode::PEI => {
+            let data = snes.read_word(instruction.data_addr)?;
+            push_word(snes, data)?;
+        }
+        OpCode::PER => {
+            let offset = snes.read_word(instruction.data_addr)?;
+            push_word(
+                snes,
+                (instruction.inst_addr as u16)
+                    .wrapping_add(3)
+                    .wrapping_add(offset),
+            )?;
         }
         OpCode::PHA => {
             if snes.cpu.P.m {
@@ -2051,18 +1777,11 @@ pub fn execute_instruction(
             }
         }
         OpCode::PLA => {
-            if snes.cpu.P.m {
-                let temp = pull_byte(snes)?;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.A &= 0xFF00;
-                snes.cpu.A |= temp as u16;
-            } else {
-                let temp = pull_word(snes)?;
-                snes.cpu.P.n = (temp & 0x8000) != 0;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.A = temp;
-            }
+            let data = match snes.cpu.m_width() {
+                Width::Byte => pull_byte(snes)? as u16,
+                Width::Word => pull_word(snes)?,
+            };
+            snes.cpu.set_a(data);
         }
         OpCode::PLP => {
             let p = pull_byte(snes)?;
@@ -2081,113 +1800,61 @@ pub fn execute_instruction(
             snes.cpu.D = temp;
         }
         OpCode::PLX => {
-            if snes.cpu.P.x {
-                let temp = pull_byte(snes)?;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.X &= 0xFF00;
-                snes.cpu.X |= temp as u16;
-            } else {
-                let temp = pull_word(snes)?;
-                snes.cpu.P.n = (temp & 0x8000) != 0;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.X = temp;
-            }
+            let data = match snes.cpu.x_width() {
+                Width::Byte => pull_byte(snes)? as u16,
+                Width::Word => pull_word(snes)?,
+            };
+            snes.cpu.set_x(data);
         }
         OpCode::PLY => {
-            if snes.cpu.P.x {
-                let temp = pull_byte(snes)?;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.Y &= 0xFF00;
-                snes.cpu.Y |= temp as u16;
-            } else {
-                let temp = pull_word(snes)?;
-                snes.cpu.P.n = (temp & 0x8000) != 0;
-                snes.cpu.P.z = temp == 0;
-                snes.cpu.Y = temp;
-            }
+            let data = match snes.cpu.x_width() {
+                Width::Byte => pull_byte(snes)? as u16,
+                Width::Word => pull_word(snes)?,
+            };
+            snes.cpu.set_y(data);
         }
         OpCode::RTS => {
-            snes.cpu.PC = pull_word(snes)?;
-            snes.cpu.PC += 1;
+            snes.cpu.PC = pull_word(snes)?.wrapping_add(1);
             return Ok(CPUExecutionResult::Return);
         }
         OpCode::RTL => {
             let pc_l = pull_byte(snes)?;
             let pc_h = pull_byte(snes)?;
-            snes.cpu.PC = u16::from_be_bytes([pc_h, pc_l]);
+            snes.cpu.PC = u16::from_be_bytes([pc_h, pc_l]).wrapping_add(1);
             snes.cpu.K = pull_byte(snes)?;
+            return Ok(CPUExecutionResult::Return);
         }
         OpCode::TAX => {
-            if snes.cpu.P.x {
-                let temp = (snes.cpu.A & 0xFF) as u8;
-                snes.cpu.X &= 0xFF00;
-                snes.cpu.X |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.X = snes.cpu.A;
-                snes.cpu.P.n = (snes.cpu.X & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.X == 0;
-            }
+            snes.cpu.set_x(snes.cpu.A);
         }
         OpCode::TAY => {
-            if snes.cpu.P.x {
-                let temp = (snes.cpu.A & 0xFF) as u8;
-                snes.cpu.Y &= 0xFF00;
-                snes.cpu.Y |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.Y = snes.cpu.A;
-                snes.cpu.P.n = (snes.cpu.Y & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.Y == 0;
-            }
+            snes.cpu.set_y(snes.cpu.A);
         }
         OpCode::TSX => {
-            if snes.cpu.P.x {
-                let temp = (snes.cpu.S & 0xFF) as u8;
-                snes.cpu.X &= 0xFF00;
-                snes.cpu.X |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.X = snes.cpu.S;
-                snes.cpu.P.n = (snes.cpu.X & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.X == 0;
-            }
+            snes.cpu.set_x(snes.cpu.S);
         }
         OpCode::TXS => {
             snes.cpu.S = snes.cpu.X;
-            snes.cpu.P.n = (snes.cpu.S & 0x8000) != 0;
-            snes.cpu.P.z = snes.cpu.S == 0;
         }
         OpCode::TXA => {
-            if snes.cpu.P.m {
-                let temp = (snes.cpu.X & 0xFF) as u8;
-                snes.cpu.A &= 0xFF00;
-                snes.cpu.A |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.A = snes.cpu.X;
-                snes.cpu.P.n = (snes.cpu.A & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.A == 0;
-            }
+            snes.cpu.set_a(snes.cpu.X);
         }
         OpCode::TYA => {
-            if snes.cpu.P.m {
-                let temp = (snes.cpu.Y & 0xFF) as u8;
-                snes.cpu.A &= 0xFF00;
-                snes.cpu.A |= temp as u16;
-                snes.cpu.P.n = (temp & 0x80) != 0;
-                snes.cpu.P.z = temp == 0;
-            } else {
-                snes.cpu.A = snes.cpu.Y;
-                snes.cpu.P.n = (snes.cpu.A & 0x8000) != 0;
-                snes.cpu.P.z = snes.cpu.A == 0;
-            }
+            snes.cpu.set_a(snes.cpu.Y);
+        }
+        OpCode::TXY => {
+            snes.cpu.set_y(snes.cpu.X);
+        }
+        OpCode::TYX => {
+            snes.cpu.set_x(snes.cpu.Y);
+        }
+        OpCode::TCS => {
+            snes.cpu.S = snes.cpu.A;
+        }
+        OpCode::TSC => {
+            snes.cpu.A = snes.cpu.S;
+            snes.cpu.P.n = (snes.cpu.A & 0x8000) != 0;
+            snes.cpu.P.z = snes.cpu.A == 0;
         }
         OpCode::TCD => {
             snes.cpu.D = snes.cpu.A;
@@ -2199,68 +1866,23 @@ pub fn execute_instruction(
             snes.cpu.P.n = (snes.cpu.A & 0x8000) != 0;
             snes.cpu.P.z = snes.cpu.A == 0;
         }
-        OpCode::TRB => {
-            if snes.cpu.P.m {
-                let val = memory::read_byte(snes, instruction.data_addr)?;
-                let result = val & ((snes.cpu.A & 0xFF) ^ 0xFF) as u8;
-                memory::write_byte(snes, instruction.data_addr, result)?;
-                snes.cpu.P.z = result == 0;
-            } else {
-                let val = memory::read_word(snes, instruction.data_addr)?;
-                let result = val & (snes.cpu.A ^ 0xFFFF);
-                memory::write_word(snes, instruction.data_addr, result)?;
-                snes.cpu.P.z = result == 0;
-            }
-        }
-        OpCode::TSB => {
-            if snes.cpu.P.m {
-                let val = memory::read_byte(snes, instruction.data_addr)?;
-                let result = val | (snes.cpu.A & 0xFF) as u8;
-                memory::write_byte(snes, instruction.data_addr, result)?;
-                snes.cpu.P.z = result == 0;
-            } else {
-                let val = memory::read_word(snes, instruction.data_addr)?;
-                let result = val | snes.cpu.A;
-                memory::write_word(snes, instruction.data_addr, result)?;
-                snes.cpu.P.z = result == 0;
-            }
-        }
-        OpCode::SBC => {
-            if snes.cpu.P.d {
-                unimplemented!("Decimal mode");
+        OpCode::TRB | OpCode::TSB => {
+            let width = snes.cpu.m_width();
+            let data = read_operand(snes, instruction, width)?;
+            // Z reflects the bits A and memory had in common before the write
+            snes.cpu.P.z = snes.cpu.A & data & width.mask() == 0;
+            let result = if instruction.opcode == OpCode::TSB {
+                data | snes.cpu.A
             } else {
-                if snes.cpu.P.m {
-                    let data = memory::read_byte(snes, instruction.data_addr)?;
-                    let a_val = (snes.cpu.A & 0xFF) as u8;
-                    let carry = if snes.cpu.P.c { 1 } else { 0 };
-                    let result = a_val.wrapping_sub(data).wrapping_sub(1 - carry);
-
-                    snes.cpu.P.z = result == 0;
-                    snes.cpu.P.n = (result & 0x80) != 0;
-                    snes.cpu.P.c = (a_val as u16 + carry as u16) >= (data as u16 + 1);
-                    snes.cpu.P.v = ((a_val ^ data) & 0x80 != 0) && ((a_val ^ result) & 0x80 != 0);
-
-                    snes.cpu.A &= 0xFF00;
-                    snes.cpu.A |= result as u16;
-                } else {
-                    let data = memory::read_word(snes, instruction.data_addr)?;
-                    let carry = if snes.cpu.P.c { 1 } else { 0 };
-                    let result = snes.cpu.A.wrapping_sub(data).wrapping_sub(1 - carry);
-
-                    snes.cpu.P.z = result == 0;
-                    snes.cpu.P.n = (result & 0x8000) != 0;
-                    snes.cpu.P.c = (snes.cpu.A as u32 + carry as u32) >= (data as u32 + 1);
-                    snes.cpu.P.v = ((snes.cpu.A ^ data) & 0x8000 != 0)
-                        && ((snes.cpu.A ^ result) & 0x8000 != 0);
-
-                    snes.cpu.A = result;
-                }
-            }
+                data & !snes.cpu.A
+            };
+            write_operand(snes, instruction, width, result & width.mask())?;
         }
         OpCode::XBA => {
             let l = snes.cpu.A & 0xFF;
             let h = (snes.cpu.A & 0xFF00) >> 8;
             snes.cpu.A = (l << 8) | h;
+            snes.cpu.set_nz(h, Width::Byte);
         }
         OpCode::XCE => {
             let temp = snes.cpu.P.c;
@@ -2272,14 +1894,50 @@ pub fn execute_instruction(
                 snes.cpu.P.m = true;
                 snes.cpu.P.x = true;
                 snes.cpu.S = (snes.cpu.S & 0x00FF) | 0x0100;
+                snes.cpu.X &= 0x00FF;
+                snes.cpu.Y &= 0x00FF;
+            }
+        }
+        OpCode::MVN | OpCode::MVP => {
+            let data = snes.read(instruction.data_addr)?;
+            snes.write(instruction.dest_addr, data)?;
+            snes.cpu.DBR = instruction.dest_addr.to_be_bytes()[1];
+            let step = if instruction.opcode == OpCode::MVN {
+                1
+            } else {
+                0xFFFF
+            };
+            snes.cpu.X = snes.cpu.X.wrapping_add(step);
+            snes.cpu.Y = snes.cpu.Y.wrapping_add(step);
+            if snes.cpu.P.x {
+                snes.cpu.X &= 0x00FF;
+                snes.cpu.Y &= 0x00FF;
             }
+            // A is always a 16 bit count, the move repeats until it underflows
+            snes.cpu.A = snes.cpu.A.wrapping_sub(1);
+            if snes.cpu.A != 0xFFFF {
+                return Ok(CPUExecutionResult::Normal);
+            }
+        }
+        OpCode::WAI => {
+            trace!("WAI at ${:06X}", instruction.inst_addr);
+            snes.cpu.state = RunState::Waiting;
         }
-        OpCode::NOP => {
+        OpCode::STP => {
+            // Only a reset gets the CPU going again, so leave PC on the STP
+            trace!("STP at ${:06X}", instruction.inst_addr);
+            snes.cpu.state = RunState::Stopped;
+            return Ok(CPUExecutionResult::Normal);
+        }
+        OpCode::NOP | OpCode::WDM => {
             // No operation - do nothing
         }
-        _ => todo!("{} Not implemented yet", instruction.opcode),
     }
-    snes.cpu.PC += instruction.length(snes.cpu.P.m, snes.cpu.P.x) as u16;
+    // PC wraps within the program bank
+    snes.cpu.PC = snes
+        .cpu
+        .PC
+        .wrapping_add(instruction.length(snes.cpu.P.m, snes.cpu.P.x) as u16);
     Ok(CPUExecutionResult::Normal)
 }
 
@@ -2290,20 +1948,22 @@ mod tests {
         CartHardware, Cartridge, ExtraHardware, InterruptVectorTable, MapMode, Region, RomHeader,
         RomSpeed,
     };
-    use crate::registers::{DMARegisters, MMIORegisters};
     use crate::Console;
 
     #[test]
-    fn test_addrmode_decode() {
-        for x in 0..=0xFF {
-            let addrmode = decode_addressing_mode(x);
-            match addrmode {
-                Result::Ok(_) => assert!(true),
-                Err(_) => {
-                    assert!(false, "{:02X}: {:?}", x, addrmode);
-                }
-            }
-        }
+    fn test_opcode_table() {
+        let mut snes = setup_test_console();
+        for byte in 0..=0xFF {
+            let info = &OPCODES[byte as usize];
+            let instruction = decode_instruction(&mut snes, byte, 0x7E0000).unwrap();
+            assert_eq!(instruction.opcode, info.opcode, "{:02X}", byte);
+            assert_eq!(instruction.mode, info.mode, "{:02X}", byte);
+            assert!((1..=4).contains(&info.length), "{:02X}", byte);
+            assert!(info.cycles >= 2, "{:02X}", byte);
+        }
+        assert_eq!(OPCODES[0xB6].mode, AddrMode::DirectY);
+        assert_eq!(OPCODES[0xBE].mode, AddrMode::AbsoluteY);
+        assert_eq!(OPCODES[0x12].length, 2);
     }
 
     #[test]
@@ -2320,7 +1980,7 @@ mod tests {
         ];
 
         for (opcode, name) in test_cases {
-            let result = decode_addressing_mode(opcode).unwrap();
+            let result = OPCODES[opcode as usize].mode.clone();
             assert_eq!(
                 result,
                 AddrMode::Accumulator,
@@ -2363,15 +2023,10 @@ mod tests {
                 expanded_header: None,
             },
             rom_data: vec![0; 32 * 1024],
+            sram: Vec::new(),
         };
 
-        let mut snes = Console {
-            cpu: CPU::new(),
-            cartridge,
-            ram: vec![0; 0x200000],
-            mmio: MMIORegisters::default(),
-            dma: DMARegisters::default(),
-        };
+        let mut snes = Console::new(cartridge);
         snes.cpu.P.e = false; // native mode
         snes.cpu.P.m = true; // 8-bit accumulator by default for tests
         snes.cpu.P.x = true; // 8-bit index by default for tests
@@ -2381,15 +2036,18 @@ mod tests {
     }
 
     // Helper to run a single instruction from a byte slice
-    fn run_test_instruction(snes: &mut Console, opcode: &[u8]) -> Result<CPUExecutionResult> {
+    fn run_test_instruction(
+        snes: &mut Console,
+        opcode: &[u8],
+    ) -> Result<CPUExecutionResult, CpuError> {
         // Write opcode to memory at current PC
         for (i, byte) in opcode.iter().enumerate() {
-            memory::write_byte(snes, snes.cpu.get_pc() + i as u32, *byte).unwrap();
+            snes.write(snes.cpu.get_pc() + i as u32, *byte).unwrap();
         }
 
-        let op = memory::read_byte(snes, snes.cpu.get_pc())?;
+        let op = snes.read(snes.cpu.get_pc()).unwrap();
         let instruction = decode_instruction(snes, op, snes.cpu.get_pc())?;
-        execute_instruction(snes, &instruction)
+        execute_instruction(snes, &instruction).map(|(result, _)| result)
     }
 
     mod load_store_tests {
@@ -2428,7 +2086,7 @@ mod tests {
             let mut snes = setup_test_console();
             snes.cpu.P.x = false; // 16-bit index
             snes.cpu.DBR = 0;
-            memory::write_word(&mut snes, 0x1234, 0xABCD).unwrap();
+            snes.write_word(0x1234, 0xABCD).unwrap();
             run_test_instruction(&mut snes, &[0xAC, 0x34, 0x12]).unwrap();
             assert_eq!(snes.cpu.Y, 0xABCD);
         }
@@ -2440,7 +2098,7 @@ mod tests {
             snes.cpu.A = 0xABCD;
             snes.cpu.DBR = 0;
             run_test_instruction(&mut snes, &[0x8D, 0x34, 0x12]).unwrap();
-            assert_eq!(memory::read_word(&snes, 0x1234).unwrap(), 0xABCD);
+            assert_eq!(snes.read_word(0x1234).unwrap(), 0xABCD);
         }
 
         #[test]
@@ -2448,9 +2106,9 @@ mod tests {
             let mut snes = setup_test_console();
             snes.cpu.P.m = false; // 16-bit
             snes.cpu.D = 0x1000;
-            memory::write_word(&mut snes, 0x1020, 0xFFFF).unwrap();
+            snes.write_word(0x1020, 0xFFFF).unwrap();
             run_test_instruction(&mut snes, &[0x64, 0x20]).unwrap();
-            assert_eq!(memory::read_word(&snes, 0x1020).unwrap(), 0x0000);
+            assert_eq!(snes.read_word(0x1020).unwrap(), 0x0000);
         }
     }
 
@@ -2505,9 +2163,9 @@ mod tests {
             let mut snes = setup_test_console();
             snes.cpu.P.m = true; // 8-bit mode
             snes.cpu.DBR = 0;
-            memory::write_byte(&mut snes, 0x1234, 0x80).unwrap();
+            snes.write(0x1234, 0x80).unwrap();
             run_test_instruction(&mut snes, &[0xCE, 0x34, 0x12]).unwrap();
-            assert_eq!(memory::read_byte(&snes, 0x1234).unwrap(), 0x7F);
+            assert_eq!(snes.read(0x1234).unwrap(), 0x7F);
         }
     }
 
@@ -2548,6 +2206,70 @@ mod tests {
             assert_eq!(snes.cpu.get_pc(), start_pc + 3);
             assert_eq!(snes.cpu.S, 0x1FF);
         }
+
+        #[test]
+        fn bcs_taken() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.c = true;
+            let start_pc = snes.cpu.get_pc();
+            let res = run_test_instruction(&mut snes, &[0xB0, 0x10]).unwrap();
+            assert_eq!(res, CPUExecutionResult::BranchTaken);
+            assert_eq!(snes.cpu.get_pc(), start_pc + 2 + 0x10);
+        }
+
+        #[test]
+        fn bvc_taken_backwards() {
+            let mut snes = setup_test_console();
+            snes.cpu.set_pc(0x7E0100);
+            snes.cpu.P.v = false;
+            run_test_instruction(&mut snes, &[0x50, 0xFC]).unwrap(); // BVC -4
+            assert_eq!(snes.cpu.get_pc(), 0x7E00FE);
+        }
+
+        #[test]
+        fn bvs_not_taken() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.v = false;
+            let start_pc = snes.cpu.get_pc();
+            let res = run_test_instruction(&mut snes, &[0x70, 0x10]).unwrap();
+            assert_eq!(res, CPUExecutionResult::Normal);
+            assert_eq!(snes.cpu.get_pc(), start_pc + 2);
+        }
+
+        #[test]
+        fn brl_always_taken() {
+            let mut snes = setup_test_console();
+            let res = run_test_instruction(&mut snes, &[0x82, 0x00, 0x01]).unwrap();
+            assert_eq!(res, CPUExecutionResult::BranchTaken);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0103);
+        }
+
+        #[test]
+        fn jml_absolute_indirect_long() {
+            let mut snes = setup_test_console();
+            snes.write_word(0x1000, 0x2345).unwrap();
+            snes.write(0x1002, 0x7E).unwrap();
+            let res = run_test_instruction(&mut snes, &[0xDC, 0x00, 0x10]).unwrap();
+            assert_eq!(res, CPUExecutionResult::Jump);
+            assert_eq!(snes.cpu.get_pc(), 0x7E2345);
+        }
+
+        #[test]
+        fn jsl_and_rtl() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+
+            // JSL $7E9034
+            run_test_instruction(&mut snes, &[0x22, 0x34, 0x90, 0x7E]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E9034);
+            assert_eq!(snes.cpu.S, 0x1FC);
+
+            // RTL
+            let res = run_test_instruction(&mut snes, &[0x6B]).unwrap();
+            assert_eq!(res, CPUExecutionResult::Return);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0004);
+            assert_eq!(snes.cpu.S, 0x1FF);
+        }
     }
 
     mod stack_tests {
@@ -2585,6 +2307,38 @@ mod tests {
             run_test_instruction(&mut snes, &[0x28]).unwrap(); // PLP
             assert_eq!(snes.cpu.p_byte(), 0b10110101); // N, M, X, I, C should be set as per initial state
         }
+
+        #[test]
+        fn pea() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            run_test_instruction(&mut snes, &[0xF4, 0x34, 0x12]).unwrap();
+            assert_eq!(snes.cpu.S, 0x1FD);
+            assert_eq!(snes.read_word(0x1FE).unwrap(), 0x1234);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0003);
+        }
+
+        #[test]
+        fn pei() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cpu.D = 0x0100;
+            snes.write_word(0x0120, 0xBEEF).unwrap();
+            run_test_instruction(&mut snes, &[0xD4, 0x20]).unwrap();
+            assert_eq!(snes.cpu.S, 0x1FD);
+            assert_eq!(snes.read_word(0x1FE).unwrap(), 0xBEEF);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0002);
+        }
+
+        #[test]
+        fn per() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            run_test_instruction(&mut snes, &[0x62, 0x10, 0x00]).unwrap();
+            assert_eq!(snes.cpu.S, 0x1FD);
+            assert_eq!(snes.read_word(0x1FE).unwrap(), 0x0013);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0003);
+        }
     }
 
     mod transfer_tests {
@@ -2622,6 +2376,42 @@ mod tests {
             run_test_instruction(&mut snes, &[0x7B]).unwrap(); // TDC
             assert_eq!(snes.cpu.A, 0x1234);
         }
+
+        #[test]
+        fn tcs_and_tsc() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.m = true; // TCS/TSC are always 16 bit
+            snes.cpu.A = 0x8123;
+            run_test_instruction(&mut snes, &[0x1B]).unwrap(); // TCS
+            assert_eq!(snes.cpu.S, 0x8123);
+
+            snes.cpu.A = 0;
+            run_test_instruction(&mut snes, &[0x3B]).unwrap(); // TSC
+            assert_eq!(snes.cpu.A, 0x8123);
+            assert!(snes.cpu.P.n);
+            assert!(!snes.cpu.P.z);
+        }
+
+        #[test]
+        fn txy_16bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = false;
+            snes.cpu.X = 0x8001;
+            run_test_instruction(&mut snes, &[0x9B]).unwrap();
+            assert_eq!(snes.cpu.Y, 0x8001);
+            assert!(snes.cpu.P.n);
+        }
+
+        #[test]
+        fn tyx_8bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = true;
+            snes.cpu.X = 0x0055;
+            snes.cpu.Y = 0x0000;
+            run_test_instruction(&mut snes, &[0xBB]).unwrap();
+            assert_eq!(snes.cpu.X, 0x0000);
+            assert!(snes.cpu.P.z);
+        }
     }
 
     mod flag_tests {
@@ -2637,6 +2427,31 @@ mod tests {
             assert!(snes.cpu.P.c);
         }
 
+        #[test]
+        fn cld_sed() {
+            let mut snes = setup_test_console();
+            run_test_instruction(&mut snes, &[0xF8]).unwrap(); // SED
+            assert!(snes.cpu.P.d);
+            run_test_instruction(&mut snes, &[0xD8]).unwrap(); // CLD
+            assert!(!snes.cpu.P.d);
+        }
+
+        #[test]
+        fn clv() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.v = true;
+            run_test_instruction(&mut snes, &[0xB8]).unwrap();
+            assert!(!snes.cpu.P.v);
+        }
+
+        #[test]
+        fn sei() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.i = false;
+            run_test_instruction(&mut snes, &[0x78]).unwrap();
+            assert!(snes.cpu.P.i);
+        }
+
         #[test]
         fn sep_rep_flags() {
             let mut snes = setup_test_console();
@@ -2672,4 +2487,1220 @@ mod tests {
             assert_eq!(snes.cpu.S, 0x01DD); // SH forced to 01
         }
     }
+
+    mod logic_tests {
+        use super::*;
+
+        #[test]
+        fn eor_immediate_8bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.m = true;
+            snes.cpu.A = 0x12F0;
+            run_test_instruction(&mut snes, &[0x49, 0x0F]).unwrap();
+            assert_eq!(snes.cpu.A, 0x12FF); // B is preserved
+            assert!(snes.cpu.P.n);
+            assert!(!snes.cpu.P.z);
+        }
+
+        #[test]
+        fn eor_direct_indirect_16bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.m = false;
+            snes.cpu.A = 0xAAAA;
+            snes.write_word(0x0010, 0x1000).unwrap();
+            snes.write_word(0x1000, 0xAAAA).unwrap();
+            run_test_instruction(&mut snes, &[0x52, 0x10]).unwrap(); // EOR ($10)
+            assert_eq!(snes.cpu.A, 0x0000);
+            assert!(snes.cpu.P.z);
+            assert!(!snes.cpu.P.n);
+        }
+    }
+
+    mod block_move_tests {
+        use super::*;
+
+        fn run_block_move(snes: &mut Console, opcode: &[u8]) {
+            let start_pc = snes.cpu.get_pc();
+            for _ in 0..0x100 {
+                run_test_instruction(snes, opcode).unwrap();
+                if snes.cpu.get_pc() != start_pc {
+                    break;
+                }
+            }
+            assert_eq!(snes.cpu.get_pc(), start_pc + 3);
+        }
+
+        #[test]
+        fn mvn() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = false;
+            snes.cpu.A = 0x0002; // Move 3 bytes
+            snes.cpu.X = 0x1000;
+            snes.cpu.Y = 0x2000;
+            for (i, byte) in [0x11, 0x22, 0x33].iter().enumerate() {
+                snes.write(0x7E1000 + i as u32, *byte).unwrap();
+            }
+            run_block_move(&mut snes, &[0x54, 0x7F, 0x7E]); // MVN $7E -> $7F
+            assert_eq!(snes.read(0x7F2000).unwrap(), 0x11);
+            assert_eq!(snes.read(0x7F2001).unwrap(), 0x22);
+            assert_eq!(snes.read(0x7F2002).unwrap(), 0x33);
+            assert_eq!(snes.cpu.A, 0xFFFF);
+            assert_eq!(snes.cpu.X, 0x1003);
+            assert_eq!(snes.cpu.Y, 0x2003);
+            assert_eq!(snes.cpu.DBR, 0x7F);
+        }
+
+        #[test]
+        fn mvp() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = false;
+            snes.cpu.A = 0x0001; // Move 2 bytes
+            snes.cpu.X = 0x1001;
+            snes.cpu.Y = 0x2001;
+            snes.write(0x7E1000, 0xAA).unwrap();
+            snes.write(0x7E1001, 0xBB).unwrap();
+            run_block_move(&mut snes, &[0x44, 0x7E, 0x7E]); // MVP $7E -> $7E
+            assert_eq!(snes.read(0x7E2000).unwrap(), 0xAA);
+            assert_eq!(snes.read(0x7E2001).unwrap(), 0xBB);
+            assert_eq!(snes.cpu.A, 0xFFFF);
+            assert_eq!(snes.cpu.X, 0x0FFF);
+            assert_eq!(snes.cpu.Y, 0x1FFF);
+        }
+    }
+
+    mod control_tests {
+        use super::*;
+
+        #[test]
+        fn cop_native() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cpu.P.i = false;
+            snes.cpu.P.d = true;
+            snes.cartridge.header.interrupt_vectors.cop = 0x9000;
+            let res = run_test_instruction(&mut snes, &[0x02, 0x42]).unwrap();
+            assert_eq!(res, CPUExecutionResult::Interrupt);
+            assert_eq!(snes.cpu.get_pc(), 0x009000);
+            assert_eq!(snes.cpu.S, 0x1FB);
+            assert_eq!(snes.read(0x1FF).unwrap(), 0x7E); // K
+            assert_eq!(snes.read_word(0x1FD).unwrap(), 0x0002); // Return address
+            assert!(snes.cpu.P.i);
+            assert!(!snes.cpu.P.d);
+        }
+
+        #[test]
+        fn wdm_is_two_byte_nop() {
+            let mut snes = setup_test_console();
+            run_test_instruction(&mut snes, &[0x42, 0xFF]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E0002);
+        }
+
+        #[test]
+        fn wai_waits() {
+            let mut snes = setup_test_console();
+            run_test_instruction(&mut snes, &[0xCB]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E0001);
+            assert_eq!(snes.cpu.state, RunState::Waiting);
+        }
+
+        #[test]
+        fn stp_halts() {
+            let mut snes = setup_test_console();
+            run_test_instruction(&mut snes, &[0xDB]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E0000);
+            assert_eq!(snes.cpu.state, RunState::Stopped);
+        }
+    }
+
+    mod emulation_tests {
+        use super::*;
+
+        fn setup_emu_console() -> Console {
+            let mut snes = setup_test_console();
+            snes.cpu.P.e = true;
+            snes.cpu.S = 0x01FF;
+            snes.cpu.set_pc(0x000200);
+            snes
+        }
+
+        #[test]
+        fn lda_is_8bit() {
+            let mut snes = setup_emu_console();
+            snes.cpu.A = 0x1234;
+            run_test_instruction(&mut snes, &[0xA9, 0x80]).unwrap();
+            assert_eq!(snes.cpu.A, 0x1280);
+            assert!(snes.cpu.P.n);
+            assert_eq!(snes.cpu.get_pc(), 0x000202);
+        }
+
+        #[test]
+        fn rep_cannot_clear_widths() {
+            let mut snes = setup_emu_console();
+            run_test_instruction(&mut snes, &[0xC2, 0x30]).unwrap();
+            assert!(snes.cpu.P.m);
+            assert!(snes.cpu.P.x);
+            assert_eq!(snes.cpu.get_pc(), 0x000202);
+        }
+
+        #[test]
+        fn stack_wraps_in_page_one() {
+            let mut snes = setup_emu_console();
+            snes.cpu.S = 0x0100;
+            snes.cpu.A = 0x42;
+            run_test_instruction(&mut snes, &[0x48]).unwrap(); // PHA
+            assert_eq!(snes.cpu.S, 0x01FF);
+            assert_eq!(snes.read(0x000100).unwrap(), 0x42);
+            snes.cpu.A = 0;
+            run_test_instruction(&mut snes, &[0x68]).unwrap(); // PLA
+            assert_eq!(snes.cpu.S, 0x0100);
+            assert_eq!(snes.cpu.A, 0x42);
+        }
+
+        #[test]
+        fn jsr_rts() {
+            let mut snes = setup_emu_console();
+            run_test_instruction(&mut snes, &[0x20, 0x00, 0x03]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x000300);
+            assert_eq!(snes.cpu.S, 0x01FD);
+            assert_eq!(snes.read_word(0x0001FE).unwrap(), 0x0202);
+            run_test_instruction(&mut snes, &[0x60]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x000203);
+            assert_eq!(snes.cpu.S, 0x01FF);
+        }
+
+        #[test]
+        fn direct_indexed_wraps_in_page() {
+            let mut snes = setup_emu_console();
+            snes.cpu.X = 0x02;
+            snes.write(0x000001, 0x55).unwrap();
+            snes.write(0x000101, 0xAA).unwrap();
+            run_test_instruction(&mut snes, &[0xB5, 0xFF]).unwrap(); // LDA $FF,X
+            assert_eq!(snes.cpu.A & 0xFF, 0x55);
+        }
+
+        #[test]
+        fn direct_pointer_wraps_in_page() {
+            let mut snes = setup_emu_console();
+            snes.cpu.DBR = 0x7E;
+            snes.write(0x0000FF, 0x00).unwrap();
+            snes.write(0x000000, 0x10).unwrap();
+            snes.write(0x7E1000, 0x66).unwrap();
+            run_test_instruction(&mut snes, &[0xB2, 0xFF]).unwrap(); // LDA ($FF)
+            assert_eq!(snes.cpu.A & 0xFF, 0x66);
+        }
+
+        #[test]
+        fn no_page_wrap_when_dl_nonzero() {
+            let mut snes = setup_emu_console();
+            snes.cpu.D = 0x0001;
+            snes.cpu.X = 0x02;
+            snes.write(0x000102, 0x77).unwrap();
+            run_test_instruction(&mut snes, &[0xB5, 0xFF]).unwrap(); // LDA $FF,X
+            assert_eq!(snes.cpu.A & 0xFF, 0x77);
+        }
+
+        #[test]
+        fn brk_pushes_emulation_frame() {
+            let mut snes = setup_emu_console();
+            snes.cpu.P.i = false;
+            snes.cpu.P.d = true;
+            snes.cartridge.header.interrupt_vectors.irq_emu = 0x8123;
+            let res = run_test_instruction(&mut snes, &[0x00, 0x00]).unwrap();
+            assert_eq!(res, CPUExecutionResult::Interrupt);
+            assert_eq!(snes.cpu.get_pc(), 0x008123);
+            assert_eq!(snes.cpu.S, 0x01FC);
+            assert_eq!(snes.read_word(0x0001FE).unwrap(), 0x0202);
+            assert_ne!(snes.read(0x0001FD).unwrap() & 0x10, 0);
+            assert!(snes.cpu.P.i);
+            assert!(!snes.cpu.P.d);
+        }
+
+        #[test]
+        fn cop_uses_emulation_vector() {
+            let mut snes = setup_emu_console();
+            snes.cartridge.header.interrupt_vectors.cop_emu = 0x8456;
+            let res = run_test_instruction(&mut snes, &[0x02, 0x00]).unwrap();
+            assert_eq!(res, CPUExecutionResult::Interrupt);
+            assert_eq!(snes.cpu.get_pc(), 0x008456);
+            assert_eq!(snes.cpu.S, 0x01FC);
+            assert_eq!(snes.read(0x0001FD).unwrap() & 0x10, 0);
+        }
+
+        #[test]
+        fn rti_pulls_no_bank() {
+            let mut snes = setup_emu_console();
+            snes.cpu.S = 0x01FC;
+            snes.write(0x0001FD, 0x03).unwrap(); // P
+            snes.write_word(0x0001FE, 0x1234).unwrap();
+            run_test_instruction(&mut snes, &[0x40]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x001234);
+            assert_eq!(snes.cpu.S, 0x01FF);
+            assert!(snes.cpu.P.c);
+            assert!(snes.cpu.P.z);
+            assert!(snes.cpu.P.m);
+            assert!(snes.cpu.P.x);
+        }
+
+        #[test]
+        fn txs_stays_in_page_one() {
+            let mut snes = setup_emu_console();
+            snes.cpu.X = 0x80;
+            run_test_instruction(&mut snes, &[0x9A]).unwrap();
+            assert_eq!(snes.cpu.S, 0x0180);
+        }
+
+        #[test]
+        fn php_sets_break_bit() {
+            let mut snes = setup_emu_console();
+            run_test_instruction(&mut snes, &[0x08]).unwrap();
+            assert_eq!(snes.read(0x0001FF).unwrap() & 0x30, 0x30);
+        }
+
+        #[test]
+        fn plp_keeps_widths() {
+            let mut snes = setup_emu_console();
+            snes.cpu.S = 0x01FE;
+            snes.write(0x0001FF, 0x00).unwrap();
+            run_test_instruction(&mut snes, &[0x28]).unwrap();
+            assert!(snes.cpu.P.m);
+            assert!(snes.cpu.P.x);
+        }
+
+        #[test]
+        fn xce_to_native() {
+            let mut snes = setup_emu_console();
+            snes.cpu.P.c = false;
+            run_test_instruction(&mut snes, &[0xFB]).unwrap();
+            assert!(!snes.cpu.P.e);
+            assert!(snes.cpu.P.c);
+            run_test_instruction(&mut snes, &[0xC2, 0x30]).unwrap();
+            assert!(!snes.cpu.P.m);
+            assert!(!snes.cpu.P.x);
+        }
+    }
+
+    mod decimal_tests {
+        use super::*;
+
+        fn to_bcd(n: u32) -> u16 {
+            let mut out = 0;
+            for i in 0..4 {
+                out |= ((n / 10u32.pow(i)) % 10) << (i * 4);
+            }
+            out as u16
+        }
+
+        fn from_bcd(n: u16) -> u32 {
+            (0..4)
+                .map(|i| ((n as u32 >> (i * 4)) & 0xF) * 10u32.pow(i))
+                .sum()
+        }
+
+        /// V as the chip sets it: the lower digits are added in decimal, then the
+        /// top digit in binary with their carry, and V comes from that binary
+        /// intermediate before the top digit is adjusted
+        fn decimal_overflow(a: u32, b: u32, carry: bool, digits: u32, subtract: bool) -> bool {
+            let low = 10u32.pow(digits - 1);
+            let shift = 4 * (digits - 1);
+            let low_carry = if subtract {
+                (a % low) as i32 - (b % low) as i32 - !carry as i32 >= 0
+            } else {
+                a % low + b % low + carry as u32 >= low
+            };
+            let a = to_bcd(a) as u32;
+            let data = match subtract {
+                true => !to_bcd(b) as u32,
+                false => to_bcd(b) as u32,
+            };
+            let top = 0xF << shift;
+            let intermediate = (a & top) + (data & top) + ((low_carry as u32) << shift);
+            !(a ^ data) & (a ^ intermediate) & (0x8 << shift) != 0
+        }
+
+        #[test]
+        fn adc_8bit_exhaustive() {
+            for a in 0..100 {
+                for b in 0..100 {
+                    for carry in [false, true] {
+                        let (result, c, v) =
+                            bcd_add(to_bcd(a), to_bcd(b), carry, Width::Byte, false);
+                        let sum = a + b + carry as u32;
+                        assert_eq!(from_bcd(result), sum % 100, "{a} + {b} + {carry}");
+                        assert_eq!(c, sum >= 100, "{a} + {b} + {carry}");
+                        let overflow = decimal_overflow(a, b, carry, 2, false);
+                        assert_eq!(v, overflow, "{a} + {b} + {carry}");
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn sbc_8bit_exhaustive() {
+            for a in 0..100 {
+                for b in 0..100 {
+                    for carry in [false, true] {
+                        let (result, c, v) =
+                            bcd_add(to_bcd(a), to_bcd(b), carry, Width::Byte, true);
+                        let diff = a as i32 - b as i32 - !carry as i32;
+                        assert_eq!(
+                            from_bcd(result),
+                            diff.rem_euclid(100) as u32,
+                            "{a} - {b} - {}",
+                            !carry
+                        );
+                        assert_eq!(c, diff >= 0, "{a} - {b} - {}", !carry);
+                        let overflow = decimal_overflow(a, b, carry, 2, true);
+                        assert_eq!(v, overflow, "{a} - {b} - {}", !carry);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn adc_sbc_16bit_sampled() {
+            for a in (0..10000).step_by(37) {
+                for b in (0..10000).step_by(41) {
+                    for carry in [false, true] {
+                        let (result, c, v) =
+                            bcd_add(to_bcd(a), to_bcd(b), carry, Width::Word, false);
+                        let sum = a + b + carry as u32;
+                        assert_eq!(from_bcd(result), sum % 10000);
+                        assert_eq!(c, sum >= 10000);
+                        assert_eq!(v, decimal_overflow(a, b, carry, 4, false));
+
+                        let (result, c, v) =
+                            bcd_add(to_bcd(a), to_bcd(b), carry, Width::Word, true);
+                        let diff = a as i32 - b as i32 - !carry as i32;
+                        assert_eq!(from_bcd(result), diff.rem_euclid(10000) as u32);
+                        assert_eq!(c, diff >= 0);
+                        assert_eq!(v, decimal_overflow(a, b, carry, 4, true));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn overflow_vectors() {
+            // (a, data, carry, subtract) -> (result, carry, overflow)
+            let cases = [
+                (0x79, 0x00, true, false, 0x80, false, true),
+                (0x24, 0x56, false, false, 0x80, false, true),
+                (0x93, 0x82, false, false, 0x75, true, true),
+                (0x89, 0x76, false, false, 0x65, true, false),
+                (0x80, 0xF0, false, false, 0xD0, true, true),
+                (0x00, 0x01, true, true, 0x99, false, false),
+                (0x80, 0x01, true, true, 0x79, true, true),
+                (0x44, 0x29, false, true, 0x14, true, false),
+            ];
+            for (a, data, carry, subtract, result, c, v) in cases {
+                assert_eq!(
+                    bcd_add(a, data, carry, Width::Byte, subtract),
+                    (result, c, v),
+                    "{a:02X} {data:02X} {carry} {subtract}"
+                );
+            }
+            assert_eq!(
+                bcd_add(0x7999, 0x0001, false, Width::Word, false),
+                (0x8000, false, true)
+            );
+        }
+
+        #[test]
+        fn adc_opcode_8bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.d = true;
+            snes.cpu.P.c = false;
+            snes.cpu.A = 0x1258;
+            run_test_instruction(&mut snes, &[0x69, 0x46]).unwrap();
+            assert_eq!(snes.cpu.A, 0x1204);
+            assert!(snes.cpu.P.c);
+            assert!(!snes.cpu.P.z);
+        }
+
+        #[test]
+        fn sbc_opcode_16bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.d = true;
+            snes.cpu.P.m = false;
+            snes.cpu.P.c = true;
+            snes.cpu.A = 0x1000;
+            run_test_instruction(&mut snes, &[0xE9, 0x01, 0x00]).unwrap();
+            assert_eq!(snes.cpu.A, 0x0999);
+            assert!(snes.cpu.P.c);
+            assert!(!snes.cpu.P.n);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0003);
+        }
+    }
+
+    mod alu_tests {
+        use super::*;
+
+        #[test]
+        fn adc_8bit_keeps_b() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0xAB7F;
+            snes.cpu.P.c = false;
+            run_test_instruction(&mut snes, &[0x69, 0x01, 0xFF]).unwrap();
+            assert_eq!(snes.cpu.A, 0xAB80);
+            assert!(snes.cpu.P.v);
+            assert!(snes.cpu.P.n);
+            assert!(!snes.cpu.P.c);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0002);
+        }
+
+        #[test]
+        fn adc_16bit_carry() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.m = false;
+            snes.cpu.A = 0xFFFF;
+            snes.cpu.P.c = true;
+            run_test_instruction(&mut snes, &[0x69, 0x00, 0x00]).unwrap();
+            assert_eq!(snes.cpu.A, 0x0000);
+            assert!(snes.cpu.P.c);
+            assert!(snes.cpu.P.z);
+            assert!(!snes.cpu.P.v);
+        }
+
+        #[test]
+        fn sbc_8bit_overflow() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0x1280;
+            snes.cpu.P.c = true;
+            run_test_instruction(&mut snes, &[0xE9, 0x01]).unwrap();
+            assert_eq!(snes.cpu.A, 0x127F);
+            assert!(snes.cpu.P.v);
+            assert!(snes.cpu.P.c);
+        }
+
+        #[test]
+        fn ora_sets_flags() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0x1200;
+            run_test_instruction(&mut snes, &[0x09, 0x80]).unwrap();
+            assert_eq!(snes.cpu.A, 0x1280);
+            assert!(snes.cpu.P.n);
+            assert!(!snes.cpu.P.z);
+        }
+
+        #[test]
+        fn asl_accumulator_8bit_keeps_b() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0x34C1;
+            run_test_instruction(&mut snes, &[0x0A]).unwrap();
+            assert_eq!(snes.cpu.A, 0x3482);
+            assert!(snes.cpu.P.c);
+            assert!(snes.cpu.P.n);
+        }
+
+        #[test]
+        fn rol_uses_top_bit_for_carry() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0x0040;
+            snes.cpu.P.c = true;
+            run_test_instruction(&mut snes, &[0x2A]).unwrap();
+            assert_eq!(snes.cpu.A, 0x0081);
+            assert!(!snes.cpu.P.c);
+        }
+
+        #[test]
+        fn ror_rotates_carry_in() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.m = false;
+            snes.cpu.A = 0x0002;
+            snes.cpu.P.c = true;
+            run_test_instruction(&mut snes, &[0x6A]).unwrap();
+            assert_eq!(snes.cpu.A, 0x8001);
+            assert!(!snes.cpu.P.c);
+            assert!(snes.cpu.P.n);
+        }
+
+        #[test]
+        fn lsr_accumulator_16bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.m = false;
+            snes.cpu.A = 0x8001;
+            run_test_instruction(&mut snes, &[0x4A]).unwrap();
+            assert_eq!(snes.cpu.A, 0x4000);
+            assert!(snes.cpu.P.c);
+            assert!(!snes.cpu.P.n);
+        }
+
+        #[test]
+        fn rol_memory_16bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.m = false;
+            snes.cpu.DBR = 0x7E;
+            snes.cpu.P.c = false;
+            snes.write_word(0x7E1000, 0x8001).unwrap();
+            run_test_instruction(&mut snes, &[0x2E, 0x00, 0x10]).unwrap();
+            assert_eq!(snes.read_word(0x7E1000).unwrap(), 0x0002);
+            assert!(snes.cpu.P.c);
+            assert!(!snes.cpu.P.z);
+        }
+
+        #[test]
+        fn cmp_8bit_ignores_b() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0xFF10;
+            run_test_instruction(&mut snes, &[0xC9, 0x10]).unwrap();
+            assert!(snes.cpu.P.z);
+            assert!(snes.cpu.P.c);
+            assert!(!snes.cpu.P.n);
+        }
+
+        #[test]
+        fn cpx_16bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = false;
+            snes.cpu.X = 0x0100;
+            run_test_instruction(&mut snes, &[0xE0, 0x00, 0x02]).unwrap();
+            assert!(!snes.cpu.P.c);
+            assert!(snes.cpu.P.n);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0003);
+        }
+
+        #[test]
+        fn cpx_16bit_n_from_bit_15() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = false;
+            snes.cpu.X = 0x0100;
+            run_test_instruction(&mut snes, &[0xE0, 0x00, 0x00]).unwrap();
+            assert!(!snes.cpu.P.n);
+            assert!(!snes.cpu.P.z);
+            assert!(snes.cpu.P.c);
+
+            snes.cpu.set_pc(0x7E0000);
+            snes.cpu.X = 0x8000;
+            run_test_instruction(&mut snes, &[0xE0, 0x00, 0x00]).unwrap();
+            assert!(snes.cpu.P.n);
+        }
+
+        #[test]
+        fn cpy_16bit_n_from_bit_15() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = false;
+            snes.cpu.Y = 0x0180;
+            run_test_instruction(&mut snes, &[0xC0, 0x00, 0x00]).unwrap();
+            assert!(!snes.cpu.P.n);
+            assert!(snes.cpu.P.c);
+
+            snes.cpu.set_pc(0x7E0000);
+            snes.cpu.Y = 0x0000;
+            run_test_instruction(&mut snes, &[0xC0, 0x01, 0x00]).unwrap();
+            assert!(snes.cpu.P.n);
+            assert!(!snes.cpu.P.c);
+        }
+
+        #[test]
+        fn cpy_8bit() {
+            let mut snes = setup_test_console();
+            snes.cpu.Y = 0x0001;
+            run_test_instruction(&mut snes, &[0xC0, 0x02]).unwrap();
+            assert!(snes.cpu.P.n);
+            assert!(!snes.cpu.P.c);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0002);
+        }
+
+        #[test]
+        fn xba_sets_nz_from_new_low_byte() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0x8000;
+            run_test_instruction(&mut snes, &[0xEB]).unwrap();
+            assert_eq!(snes.cpu.A, 0x0080);
+            assert!(snes.cpu.P.n);
+            assert!(!snes.cpu.P.z);
+
+            snes.cpu.set_pc(0x7E0000);
+            snes.cpu.A = 0x00FF;
+            run_test_instruction(&mut snes, &[0xEB]).unwrap();
+            assert_eq!(snes.cpu.A, 0xFF00);
+            assert!(snes.cpu.P.z);
+            assert!(!snes.cpu.P.n);
+        }
+
+        #[test]
+        fn bit_memory_sets_n_v() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.cpu.A = 0x0001;
+            snes.write(0x7E1000, 0xC0).unwrap();
+            run_test_instruction(&mut snes, &[0x2C, 0x00, 0x10]).unwrap();
+            assert!(snes.cpu.P.n);
+            assert!(snes.cpu.P.v);
+            assert!(snes.cpu.P.z);
+        }
+
+        #[test]
+        fn bit_immediate_only_sets_z() {
+            let mut snes = setup_test_console();
+            snes.cpu.A = 0x0080;
+            snes.cpu.P.n = false;
+            snes.cpu.P.v = false;
+            run_test_instruction(&mut snes, &[0x89, 0xC0]).unwrap();
+            assert!(!snes.cpu.P.z);
+            assert!(!snes.cpu.P.n);
+            assert!(!snes.cpu.P.v);
+        }
+
+        #[test]
+        fn inc_memory_8bit_wraps() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.write_word(0x7E1000, 0x12FF).unwrap();
+            run_test_instruction(&mut snes, &[0xEE, 0x00, 0x10]).unwrap();
+            assert_eq!(snes.read_word(0x7E1000).unwrap(), 0x1200);
+            assert!(snes.cpu.P.z);
+        }
+
+        #[test]
+        fn tsb_z_from_original_value() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.cpu.A = 0x0001;
+            snes.write(0x7E1000, 0x02).unwrap();
+            run_test_instruction(&mut snes, &[0x0C, 0x00, 0x10]).unwrap();
+            assert_eq!(snes.read(0x7E1000).unwrap(), 0x03);
+            assert!(snes.cpu.P.z);
+        }
+
+        #[test]
+        fn trb_clears_bits() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.cpu.A = 0x0003;
+            snes.write(0x7E1000, 0x06).unwrap();
+            run_test_instruction(&mut snes, &[0x1C, 0x00, 0x10]).unwrap();
+            assert_eq!(snes.read(0x7E1000).unwrap(), 0x04);
+            assert!(!snes.cpu.P.z);
+        }
+
+        #[test]
+        fn tax_16bit_index_from_8bit_a() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.x = false;
+            snes.cpu.A = 0x8034;
+            run_test_instruction(&mut snes, &[0xAA]).unwrap();
+            assert_eq!(snes.cpu.X, 0x8034);
+            assert!(snes.cpu.P.n);
+        }
+    }
+
+    mod cycle_tests {
+        use super::*;
+
+        fn run_cycles(snes: &mut Console, opcode: &[u8]) -> u8 {
+            for (i, byte) in opcode.iter().enumerate() {
+                memory::poke_byte(snes, snes.cpu.get_pc() + i as u32, *byte).unwrap();
+            }
+            step(snes).unwrap().1
+        }
+
+        #[test]
+        fn immediate_width_penalty() {
+            let mut snes = setup_test_console();
+            assert_eq!(run_cycles(&mut snes, &[0xA9, 0x00]), 2);
+            snes.cpu.P.m = false;
+            assert_eq!(run_cycles(&mut snes, &[0xA9, 0x00, 0x00]), 3);
+            snes.cpu.P.x = false;
+            assert_eq!(run_cycles(&mut snes, &[0xA2, 0x00, 0x00]), 3);
+        }
+
+        #[test]
+        fn direct_page_penalty() {
+            let mut snes = setup_test_console();
+            assert_eq!(run_cycles(&mut snes, &[0xA5, 0x10]), 3);
+            snes.cpu.D = 0x0001;
+            assert_eq!(run_cycles(&mut snes, &[0xA5, 0x10]), 4);
+        }
+
+        #[test]
+        fn indexed_page_cross_penalty() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.cpu.X = 0x01;
+            assert_eq!(run_cycles(&mut snes, &[0xBD, 0x00, 0x10]), 4);
+            assert_eq!(run_cycles(&mut snes, &[0xBD, 0xFF, 0x10]), 5);
+            snes.cpu.P.x = false;
+            assert_eq!(run_cycles(&mut snes, &[0xBD, 0x00, 0x10]), 5);
+        }
+
+        #[test]
+        fn indexed_store_always_pays() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.cpu.X = 0x01;
+            assert_eq!(run_cycles(&mut snes, &[0x9D, 0x00, 0x10]), 5);
+        }
+
+        #[test]
+        fn read_modify_write() {
+            let mut snes = setup_test_console();
+            assert_eq!(run_cycles(&mut snes, &[0x06, 0x10]), 5);
+            snes.cpu.P.m = false;
+            assert_eq!(run_cycles(&mut snes, &[0x06, 0x10]), 7);
+            assert_eq!(run_cycles(&mut snes, &[0x0A]), 2);
+        }
+
+        #[test]
+        fn branch_taken_penalty() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.z = false;
+            assert_eq!(run_cycles(&mut snes, &[0xF0, 0x10]), 2);
+            snes.cpu.P.z = true;
+            assert_eq!(run_cycles(&mut snes, &[0xF0, 0x10]), 3);
+        }
+
+        #[test]
+        fn branch_page_cross_in_emulation_mode() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.e = true;
+            snes.cpu.S = 0x01FF;
+            snes.cpu.set_pc(0x0000F0);
+            snes.cpu.P.z = true;
+            assert_eq!(run_cycles(&mut snes, &[0xF0, 0x20]), 4);
+        }
+
+        #[test]
+        fn master_clock_advances() {
+            let mut snes = setup_test_console();
+            run_cycles(&mut snes, &[0xEA]);
+            run_cycles(&mut snes, &[0xEA]);
+            // One WRAM fetch and one internal cycle each
+            assert_eq!(snes.master_clock, 2 * (8 + 6));
+        }
+
+        #[test]
+        fn fast_rom_needs_memsel() {
+            let mut snes = setup_test_console();
+            snes.cartridge.header.rom_speed = RomSpeed::Fast;
+            snes.cartridge.rom_data[0] = 0xA9; // LDA #$00
+            snes.mmio.MEMSEL = 0x01;
+            snes.cpu.set_pc(0x808000);
+            step(&mut snes).unwrap();
+            assert_eq!(snes.master_clock, 2 * 6);
+            snes.mmio.MEMSEL = 0x00;
+            snes.cpu.set_pc(0x808000);
+            step(&mut snes).unwrap();
+            assert_eq!(snes.master_clock, 2 * 6 + 2 * 8);
+        }
+
+        #[test]
+        fn xslow_access() {
+            let mut snes = setup_test_console();
+            snes.write(0x7E0000, 0xAD).unwrap();
+            snes.write_word(0x7E0001, 0x4100).unwrap();
+            snes.cpu.DBR = 0x00;
+            let instruction = decode_instruction(&mut snes, 0xAD, 0x7E0000).unwrap();
+            let cycles = calculate_cycles(&snes, &instruction);
+            assert_eq!(cycles, 4);
+            assert_eq!(
+                calculate_master_cycles(&snes, &instruction, cycles),
+                3 * 8 + 12
+            );
+        }
+
+        #[test]
+        fn step_reports_cycles() {
+            let mut snes = setup_test_console();
+            snes.write(0x7E0000, 0x48).unwrap(); // PHA
+            snes.cpu.S = 0x1FF;
+            let (result, cycles) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Normal);
+            assert_eq!(cycles, 3);
+        }
+
+        #[test]
+        fn multiply_result_counts_cpu_cycles() {
+            let mut snes = setup_test_console();
+            #[rustfmt::skip]
+            let program = [
+                0x8D, 0x03, 0x42, // STA WRMPYB
+                0xAD, 0x16, 0x42, // LDA RDMPYL
+                0x8D, 0x03, 0x42, // STA WRMPYB
+                0xEA,             // NOP
+                0xEA,             // NOP
+                0xAD, 0x16, 0x42, // LDA RDMPYL
+            ];
+            for (i, byte) in program.iter().enumerate() {
+                memory::poke_byte(&mut snes, 0x7E0000 + i as u32, *byte).unwrap();
+            }
+            snes.mmio.WRMPYA = 0xFF;
+            snes.cpu.DBR = 0x00;
+            snes.cpu.A = 0x03;
+            step(&mut snes).unwrap();
+            step(&mut snes).unwrap();
+            // Read 4 cycles after the write, with 4 bits of WRMPYA counted
+            assert_eq!(snes.cpu.A, 0x0F * 0x03);
+            snes.cpu.A = 0x03;
+            for _ in 0..4 {
+                step(&mut snes).unwrap();
+            }
+            // 8 cycles later the product is done
+            assert_eq!(snes.cpu.A, (0xFF * 0x03) & 0xFF);
+        }
+    }
+
+    mod interrupt_tests {
+        use super::*;
+
+        #[test]
+        fn nmi_native_frame() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cpu.P.d = true;
+            snes.cpu.set_pc(0x7E1234);
+            snes.cartridge.header.interrupt_vectors.nmi = 0x9000;
+            snes.cpu.nmi_pending = true;
+            let (result, cycles) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Interrupt);
+            assert_eq!(cycles, 8);
+            assert!(!snes.cpu.nmi_pending);
+            assert_eq!(snes.cpu.get_pc(), 0x009000);
+            assert_eq!(snes.cpu.S, 0x1FB);
+            assert_eq!(snes.read(0x1FF).unwrap(), 0x7E);
+            assert_eq!(snes.read_word(0x1FD).unwrap(), 0x1234);
+            assert!(snes.cpu.P.i);
+            assert!(!snes.cpu.P.d);
+        }
+
+        #[test]
+        fn nmi_emulation_frame() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.e = true;
+            snes.cpu.S = 0x01FF;
+            snes.cpu.set_pc(0x000200);
+            snes.cartridge.header.interrupt_vectors.nmi_emu = 0x9100;
+            snes.cpu.nmi_pending = true;
+            let (_, cycles) = step(&mut snes).unwrap();
+            assert_eq!(cycles, 7);
+            assert_eq!(snes.cpu.get_pc(), 0x009100);
+            assert_eq!(snes.cpu.S, 0x01FC);
+            assert_eq!(snes.read_word(0x01FE).unwrap(), 0x0200);
+            assert_eq!(snes.read(0x01FD).unwrap() & 0x10, 0);
+        }
+
+        #[test]
+        fn irq_respects_i() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cpu.P.i = true;
+            snes.cpu.irq_pending = true;
+            snes.write(0x7E0000, 0xEA).unwrap();
+            let (result, _) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Normal);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0001);
+
+            snes.cpu.P.i = false;
+            snes.cartridge.header.interrupt_vectors.irq = 0x9200;
+            let (result, _) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Interrupt);
+            assert_eq!(snes.cpu.get_pc(), 0x009200);
+            // The line stays asserted until the device releases it
+            assert!(snes.cpu.irq_pending);
+            assert!(snes.cpu.P.i);
+        }
+
+        #[test]
+        fn nmi_ignores_i() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cpu.P.i = true;
+            snes.cpu.nmi_pending = true;
+            snes.cpu.irq_pending = true;
+            snes.cartridge.header.interrupt_vectors.nmi = 0x9000;
+            step(&mut snes).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x009000);
+        }
+
+        #[test]
+        fn brk_native_frame() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cpu.P.i = false;
+            snes.cpu.set_pc(0x7E1234);
+            snes.cartridge.header.interrupt_vectors.brk = 0x9300;
+            let res = run_test_instruction(&mut snes, &[0x00, 0x00]).unwrap();
+            assert_eq!(res, CPUExecutionResult::Interrupt);
+            assert_eq!(snes.cpu.get_pc(), 0x009300);
+            assert_eq!(snes.read(0x1FF).unwrap(), 0x7E);
+            assert_eq!(snes.read_word(0x1FD).unwrap(), 0x1236);
+            assert!(snes.cpu.P.i);
+        }
+
+        #[test]
+        fn abort_emulation_vector() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.e = true;
+            snes.cpu.S = 0x01FF;
+            snes.cartridge.header.interrupt_vectors.abort_emu = 0x9400;
+            interrupt(&mut snes, Interrupt::ABORT, 0x1000).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x009400);
+            assert_eq!(snes.cpu.S, 0x01FC);
+        }
+
+        #[test]
+        fn rti_returns_from_nmi() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cpu.P.i = false;
+            snes.cpu.P.c = true;
+            snes.cpu.set_pc(0x7E1234);
+            snes.cartridge.header.interrupt_vectors.nmi = 0x0300;
+            snes.cpu.nmi_pending = true;
+            step(&mut snes).unwrap();
+            snes.cpu.P.c = false;
+            snes.write(0x000300, 0x40).unwrap();
+            step(&mut snes).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E1234);
+            assert_eq!(snes.cpu.S, 0x1FF);
+            assert!(!snes.cpu.P.i);
+            assert!(snes.cpu.P.c);
+        }
+    }
+
+    mod run_state_tests {
+        use super::*;
+
+        #[test]
+        fn waiting_cpu_idles() {
+            let mut snes = setup_test_console();
+            run_test_instruction(&mut snes, &[0xCB, 0xEA]).unwrap();
+            let clock = snes.master_clock;
+            let (result, cycles) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Idle);
+            assert_eq!(cycles, 1);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0001);
+            assert!(snes.master_clock > clock);
+        }
+
+        #[test]
+        fn nmi_wakes_wai() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x1FF;
+            snes.cartridge.header.interrupt_vectors.nmi = 0x9000;
+            run_test_instruction(&mut snes, &[0xCB]).unwrap();
+            snes.cpu.nmi_pending = true;
+            let (result, _) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Interrupt);
+            assert_eq!(snes.cpu.state, RunState::Running);
+            assert_eq!(snes.cpu.get_pc(), 0x009000);
+            assert_eq!(snes.read_word(0x1FD).unwrap(), 0x0001);
+        }
+
+        #[test]
+        fn masked_irq_wakes_wai_without_entering_handler() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.i = true;
+            run_test_instruction(&mut snes, &[0xCB, 0xEA]).unwrap();
+            snes.cpu.irq_pending = true;
+            let (result, _) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Normal);
+            assert_eq!(snes.cpu.state, RunState::Running);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0002);
+        }
+
+        #[test]
+        fn stp_ignores_interrupts() {
+            let mut snes = setup_test_console();
+            run_test_instruction(&mut snes, &[0xDB]).unwrap();
+            snes.cpu.nmi_pending = true;
+            snes.cpu.irq_pending = true;
+            let (result, _) = step(&mut snes).unwrap();
+            assert_eq!(result, CPUExecutionResult::Idle);
+            assert_eq!(snes.cpu.state, RunState::Stopped);
+        }
+    }
+
+    mod error_tests {
+        use super::*;
+
+        #[test]
+        fn store_to_rom_reports_instruction() {
+            let mut snes = setup_test_console();
+            let err = run_test_instruction(&mut snes, &[0x8F, 0x00, 0x80, 0x00]).unwrap_err();
+            match err {
+                CpuError::Memory {
+                    pc,
+                    opcode,
+                    mode,
+                    source,
+                } => {
+                    assert_eq!(pc, 0x7E0000);
+                    assert_eq!(opcode, OpCode::STA);
+                    assert_eq!(mode, AddrMode::Long);
+                    assert_eq!(
+                        source,
+                        MemoryError::RomWrite {
+                            addr: 0x008000,
+                            data: 0x00
+                        }
+                    );
+                }
+                e => panic!("unexpected error {:?}", e),
+            }
+        }
+
+        #[test]
+        fn fetch_from_unmapped_address() {
+            let mut snes = setup_test_console();
+            snes.strict_bus = true;
+            snes.cpu.set_pc(0x002100);
+            let err = step(&mut snes).unwrap_err();
+            assert!(matches!(
+                err,
+                CpuError::Fetch {
+                    pc: 0x002100,
+                    source: MemoryError::Unmapped(0x002100)
+                }
+            ));
+        }
+    }
+
+    mod addressing_tests {
+        use super::*;
+
+        fn decode_at(snes: &mut Console, opcode: &[u8]) -> InstructionContext {
+            for (i, byte) in opcode.iter().enumerate() {
+                snes.write(snes.cpu.get_pc() + i as u32, *byte).unwrap();
+            }
+            decode_instruction(snes, opcode[0], snes.cpu.get_pc()).unwrap()
+        }
+
+        #[test]
+        fn pc_wraps_within_bank() {
+            let mut snes = setup_test_console();
+            snes.cpu.set_pc(0x7EFFFF);
+            run_test_instruction(&mut snes, &[0xEA]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E0000);
+
+            // LDA # with its operand in the last byte of the bank
+            snes.cpu.set_pc(0x7EFFFE);
+            run_test_instruction(&mut snes, &[0xA9, 0x42]).unwrap();
+            assert_eq!(snes.cpu.A & 0xFF, 0x42);
+            assert_eq!(snes.cpu.get_pc(), 0x7E0000);
+
+            snes.cpu.P.e = true;
+            snes.cpu.set_pc(0x7EFFFF);
+            run_test_instruction(&mut snes, &[0x08]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E0000);
+        }
+
+        #[test]
+        fn rts_from_ffff_wraps() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0x01FD;
+            snes.write_word(0x0001FE, 0xFFFF).unwrap();
+            run_test_instruction(&mut snes, &[0x60]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E0000);
+        }
+
+        #[test]
+        fn direct_page_wraps_in_bank_zero() {
+            let mut snes = setup_test_console();
+            snes.cpu.D = 0xFFF0;
+            assert_eq!(decode_at(&mut snes, &[0xA5, 0x20]).data_addr, 0x000010);
+            snes.cpu.D = 0xFF00;
+            snes.cpu.P.x = false;
+            snes.cpu.X = 0x0200;
+            assert_eq!(decode_at(&mut snes, &[0xB5, 0x10]).data_addr, 0x000110);
+        }
+
+        #[test]
+        fn emulation_direct_page_wraps_in_page() {
+            let mut snes = setup_test_console();
+            snes.cpu.P.e = true;
+            snes.cpu.D = 0x0100;
+            snes.cpu.X = 0x20;
+            assert_eq!(decode_at(&mut snes, &[0xB5, 0xF0]).data_addr, 0x000110);
+            // No page wrap once DL is not zero
+            snes.cpu.D = 0x0101;
+            assert_eq!(decode_at(&mut snes, &[0xB5, 0xF0]).data_addr, 0x000211);
+        }
+
+        #[test]
+        fn stack_relative_wraps_in_bank_zero() {
+            let mut snes = setup_test_console();
+            snes.cpu.S = 0xFFF0;
+            assert_eq!(decode_at(&mut snes, &[0xA3, 0x20]).data_addr, 0x000010);
+        }
+
+        #[test]
+        fn direct_long_pointer() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.write(0x000010, 0x00).unwrap();
+            snes.write_word(0x000011, 0x0580).unwrap();
+            assert_eq!(decode_at(&mut snes, &[0xA7, 0x10]).data_addr, 0x058000);
+            snes.cpu.Y = 0x10;
+            let instruction = decode_at(&mut snes, &[0xB7, 0x10]);
+            assert_eq!(instruction.data_addr, 0x058010);
+            assert!(!instruction.crossed_page);
+        }
+
+        #[test]
+        fn indexed_indirect_jump_dereferences() {
+            let mut snes = setup_test_console();
+            snes.cpu.X = 0x02;
+            snes.write_word(0x7E1002, 0x3456).unwrap();
+            run_test_instruction(&mut snes, &[0x7C, 0x00, 0x10]).unwrap();
+            assert_eq!(snes.cpu.get_pc(), 0x7E3456);
+        }
+
+        #[test]
+        fn absolute_indirect_pointer_wraps_in_bank_zero() {
+            let mut snes = setup_test_console();
+            snes.cpu.set_pc(0x7E2000);
+            snes.cartridge.rom_data[0x7FFF] = 0x34;
+            snes.write(0x000000, 0x12).unwrap();
+            assert_eq!(
+                decode_at(&mut snes, &[0x6C, 0xFF, 0xFF]).data_addr,
+                0x7E1234
+            );
+        }
+
+        #[test]
+        fn indexing_carries_into_next_bank() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.cpu.X = 0x01;
+            let instruction = decode_at(&mut snes, &[0xBD, 0xFF, 0xFF]);
+            assert_eq!(instruction.data_addr, 0x7F0000);
+            assert!(instruction.crossed_page);
+            assert_eq!(calculate_cycles(&snes, &instruction), 5);
+            let instruction = decode_at(&mut snes, &[0xBF, 0xFF, 0xFF, 0xFF]);
+            assert_eq!(instruction.data_addr, 0x000000);
+        }
+
+        #[test]
+        fn indirect_indexed_reports_page_cross() {
+            let mut snes = setup_test_console();
+            snes.cpu.DBR = 0x7E;
+            snes.write_word(0x000010, 0x10F0).unwrap();
+            snes.cpu.Y = 0x08;
+            let instruction = decode_at(&mut snes, &[0xB1, 0x10]);
+            assert_eq!(instruction.data_addr, 0x7E10F8);
+            assert!(!instruction.crossed_page);
+            assert_eq!(calculate_cycles(&snes, &instruction), 5);
+            snes.cpu.Y = 0x10;
+            let instruction = decode_at(&mut snes, &[0xB1, 0x10]);
+            assert_eq!(instruction.data_addr, 0x7E1100);
+            assert!(instruction.crossed_page);
+            assert_eq!(calculate_cycles(&snes, &instruction), 6);
+        }
+
+        #[test]
+        fn operands_wrap_in_program_bank() {
+            let mut snes = setup_test_console();
+            snes.cpu.set_pc(0x7EFFFF);
+            let instruction = decode_at(&mut snes, &[0xA9]);
+            assert_eq!(instruction.data_addr, 0x7E0000);
+        }
+
+        #[test]
+        fn backward_branch_target() {
+            let mut snes = setup_test_console();
+            snes.cpu.set_pc(0x7E2000);
+            assert_eq!(decode_at(&mut snes, &[0x80, 0xFE]).data_addr, 0x7E2000);
+        }
+    }
 }
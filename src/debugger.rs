@@ -1,5 +1,7 @@
 use super::*;
 use crate::cpu::*;
+use crate::opcodes::{self, OPCODES};
+use crate::simulation::Simulation;
 use ahash::AHashMap;
 use color_eyre::Result;
 use thiserror::Error;
@@ -28,7 +30,7 @@ pub struct DisassemblerLine {
     pub disassembled: InstructionWrapper,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DisassemblerContext {
     pub lines: Vec<DisassemblerLine>,
     pub branchtable: Vec<usize>,
@@ -37,18 +39,6 @@ pub struct DisassemblerContext {
     pub endloc: u32,
 }
 
-impl Default for DisassemblerContext {
-    fn default() -> Self {
-        DisassemblerContext {
-            lines: Vec::default(),
-            branchtable: Vec::default(),
-            branchdepth: 0,
-            startloc: 0,
-            endloc: 0,
-        }
-    }
-}
-
 impl std::fmt::Display for InstructionWrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.instruction.mode {
@@ -59,7 +49,7 @@ impl std::fmt::Display for InstructionWrapper {
                     self.instruction.inst_addr,
                     self.instruction.opcode,
                     self.instruction.data_addr,
-                    self.instruction.dest_addr.unwrap(),
+                    self.instruction.dest_addr,
                     self.instruction.mode
                 )
             }
@@ -70,8 +60,8 @@ impl std::fmt::Display for InstructionWrapper {
                     self.instruction.inst_addr, self.instruction.opcode, self.instruction.mode
                 )
             }
-            AddrMode::Immediate => match &self.instruction.opcode {
-                OpCode::REP | OpCode::SEP | OpCode::WDM => {
+            AddrMode::Immediate => {
+                if self.instruction.length(self.status.m, self.status.x) == 2 {
                     write!(
                         f,
                         "${:06X}: {} #${:02X} ({})",
@@ -80,8 +70,7 @@ impl std::fmt::Display for InstructionWrapper {
                         self.data & 0xFF,
                         self.instruction.mode
                     )
-                }
-                OpCode::PEA | OpCode::PER => {
+                } else {
                     write!(
                         f,
                         "${:06X}: {} #${:04X} ({})",
@@ -91,28 +80,7 @@ impl std::fmt::Display for InstructionWrapper {
                         self.instruction.mode
                     )
                 }
-                _ => {
-                    if self.status.m {
-                        write!(
-                            f,
-                            "${:06X}: {} #${:02X} ({})",
-                            self.instruction.inst_addr,
-                            self.instruction.opcode,
-                            self.data & 0xFF,
-                            self.instruction.mode
-                        )
-                    } else {
-                        write!(
-                            f,
-                            "${:06X}: {} #${:04X} ({})",
-                            self.instruction.inst_addr,
-                            self.instruction.opcode,
-                            self.data,
-                            self.instruction.mode
-                        )
-                    }
-                }
-            },
+            }
             _ => {
                 write!(
                     f,
@@ -136,7 +104,11 @@ pub struct DebugState {
 
 #[derive(Debug, Error)]
 pub(crate) enum DisassemblerError {
-    DisassemblyError(DisassemblyError),
+    DisassemblyError(Box<DisassemblyError>),
+    #[error(transparent)]
+    Cpu(#[from] cpu::CpuError),
+    #[error(transparent)]
+    Memory(#[from] memory::MemoryError),
     #[error(transparent)]
     Other(#[from] color_eyre::eyre::Report),
 }
@@ -153,9 +125,9 @@ impl std::fmt::Display for DisassemblyError {
         let len = &self.instructions.len();
         for (i, instr) in self.instructions.iter().enumerate() {
             if i == len - 1 {
-                write!(f, "=> {:} <=\n", instr)?;
+                writeln!(f, "=> {:} <=", instr)?;
             } else {
-                write!(f, "{:}\n", instr)?;
+                writeln!(f, "{:}", instr)?;
             }
         }
         Ok(())
@@ -166,96 +138,39 @@ impl std::fmt::Display for DisassemblerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DisassemblerError::DisassemblyError(e) => e.fmt(f),
+            DisassemblerError::Cpu(e) => e.fmt(f),
+            DisassemblerError::Memory(e) => e.fmt(f),
             DisassemblerError::Other(e) => e.fmt(f),
         }
     }
 }
 
-fn sets_m(snes: &Console, stack: &Vec<DebugState>, instr: &InstructionContext) -> Result<bool> {
-    Ok(match instr.opcode {
-        OpCode::SEP => (memory::peek_byte(snes, instr.data_addr)? & 0x20) != 0,
-        OpCode::PLP => {
-            if !stack.is_empty() {
-                if snes.cpu.P.e {
-                    (memory::peek_byte(snes, (snes.cpu.S + 1) as u32)? & 0x20) != 0
-                } else {
-                    (memory::peek_byte(
-                        snes,
-                        u32::from_be_bytes([0x00, 0x00, 0x01, snes.cpu.S as u8]) + 1,
-                    )? & 0x20)
-                        != 0
-                }
-            } else {
-                stack.last().unwrap().m
-            }
-        }
-        _ => false,
-    })
-}
-
-fn clears_m(snes: &Console, stack: &Vec<DebugState>, instr: &InstructionContext) -> Result<bool> {
-    Ok(match instr.opcode {
-        OpCode::REP => (memory::peek_byte(snes, instr.data_addr)? & 0x20) != 0,
-        OpCode::PLP => {
-            if !stack.is_empty() {
-                if snes.cpu.P.e {
-                    (memory::peek_byte(snes, (snes.cpu.S + 1) as u32)? & 0x20) == 0
-                } else {
-                    (memory::peek_byte(
-                        snes,
-                        u32::from_be_bytes([0x00, 0x00, 0x01, snes.cpu.S as u8]) + 1,
-                    )? & 0x20)
-                        == 0
-                }
-            } else {
-                !stack.last().unwrap().m
-            }
-        }
-        _ => false,
-    })
-}
-
-fn sets_x(snes: &Console, stack: &Vec<DebugState>, instr: &InstructionContext) -> Result<bool> {
-    Ok(match instr.opcode {
-        OpCode::SEP => (memory::peek_byte(snes, instr.data_addr)? & 0x10) != 0,
-        OpCode::PLP => {
-            if !stack.is_empty() {
-                if snes.cpu.P.e {
-                    (memory::peek_byte(snes, (snes.cpu.S + 1) as u32)? & 0x10) != 0
-                } else {
-                    (memory::peek_byte(
-                        snes,
-                        u32::from_be_bytes([0x00, 0x00, 0x01, snes.cpu.S as u8]) + 1,
-                    )? & 0x10)
-                        != 0
-                }
-            } else {
-                stack.last().unwrap().x
-            }
-        }
-        _ => false,
-    })
-}
-
-fn clears_x(snes: &Console, stack: &Vec<DebugState>, instr: &InstructionContext) -> Result<bool> {
+/// The M and X bits an instruction sets and clears, as (set, cleared), for
+/// the opcodes the table marks as changing register widths
+fn width_changes(snes: &Console, instr: &InstructionContext) -> Result<(u8, u8)> {
+    let info = &OPCODES[instr.byte as usize];
+    let mut bits = 0;
+    if info.changes_m() {
+        bits |= opcodes::M;
+    }
+    if info.changes_x() {
+        bits |= opcodes::X;
+    }
     Ok(match instr.opcode {
-        OpCode::SEP => (memory::peek_byte(snes, instr.data_addr)? & 0x10) == 0,
+        _ if bits == 0 => (0, 0),
+        OpCode::SEP => (snes.peek(instr.data_addr)? & bits, 0),
+        OpCode::REP => (0, snes.peek(instr.data_addr)? & bits),
         OpCode::PLP => {
-            if !stack.is_empty() {
-                if snes.cpu.P.e {
-                    (memory::peek_byte(snes, (snes.cpu.S + 1) as u32)? & 0x10) == 0
-                } else {
-                    (memory::peek_byte(
-                        snes,
-                        u32::from_be_bytes([0x00, 0x00, 0x01, snes.cpu.S as u8]) + 1,
-                    )? & 0x10)
-                        == 0
-                }
-            } else {
-                !stack.last().unwrap().x
-            }
+            let top = match snes.cpu.P.e {
+                true => 0x0100 | (snes.cpu.S as u8).wrapping_add(1) as u32,
+                false => snes.cpu.S.wrapping_add(1) as u32,
+            };
+            let p = snes.peek(top)?;
+            (p & bits, !p & bits)
         }
-        _ => false,
+        // Entering emulation mode forces 8 bit registers
+        OpCode::XCE if snes.cpu.P.c => (bits, 0),
+        _ => (0, 0),
     })
 }
 
@@ -267,18 +182,19 @@ fn clears_e(snes: &Console, instr: &InstructionContext) -> bool {
 }
 
 pub fn debug_simulation(
-    snes: &Console,
+    snes: &mut Console,
     maxlines: usize,
 ) -> Result<DisassemblerContext, DisassemblerError> {
-    let mut snes_sim = snes.clone();
-    let start = snes.cpu.get_pc();
+    let mut snes_sim = Simulation::new(snes);
+    let start = snes_sim.cpu.get_pc();
     let mut cycle = 0;
     let mut instructions: Vec<InstructionWrapper> = Vec::with_capacity(30);
     let mut knowninstructions: AHashMap<u32, usize> = AHashMap::new();
     let mut branchinstructions = Vec::<usize>::default();
     loop {
-        let op = memory::read_byte(&snes_sim, snes_sim.cpu.get_pc())?;
-        let instr = cpu::decode_instruction(&snes_sim, op, snes_sim.cpu.get_pc())?;
+        let pc = snes_sim.cpu.get_pc();
+        let op = snes_sim.read(pc)?;
+        let instr = cpu::decode_instruction(&mut snes_sim, op, pc)?;
         let branchto = if instr.opcode.is_branch() && instr.data_addr > start {
             branchinstructions.push(cycle);
             Some(instr.data_addr)
@@ -293,9 +209,9 @@ pub fn debug_simulation(
                 e: snes_sim.cpu.P.e,
             },
             branchfrom: Vec::<u32>::default(),
-            branchto: branchto,
+            branchto,
             data: match instr.mode {
-                AddrMode::Immediate => memory::read_word(&snes_sim, instr.data_addr)?,
+                AddrMode::Immediate => snes_sim.read_word(instr.data_addr)?,
                 _ => 0x00,
             },
             instruction: instr.clone(),
@@ -306,16 +222,20 @@ pub fn debug_simulation(
             || instr.opcode.is_return()
             || instr.opcode.is_subroutine()
         {
-            snes_sim.cpu.PC += instr.length(snes.cpu.P.m, snes.cpu.P.x) as u16;
+            // Widths as the simulation has them now, after any REP or SEP
+            let length = instr.length(snes_sim.cpu.P.m, snes_sim.cpu.P.x);
+            snes_sim.cpu.PC = snes_sim.cpu.PC.wrapping_add(length as u16);
         } else {
             let res = match cpu::execute_instruction(&mut snes_sim, &instr) {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(DisassemblerError::DisassemblyError(DisassemblyError {
-                        instructions: instructions.clone(),
-                        status: snes_sim.clone(),
-                        source: e,
-                    }))
+                    return Err(DisassemblerError::DisassemblyError(Box::new(
+                        DisassemblyError {
+                            instructions: instructions.clone(),
+                            status: (*snes_sim).clone(),
+                            source: e.into(),
+                        },
+                    )))
                 }
             };
         }
@@ -351,36 +271,36 @@ pub fn debug_simulation(
             disassembled: x.clone(),
         })
         .collect();
-    return Ok(DisassemblerContext {
+    Ok(DisassemblerContext {
         lines: disassembler_lines,
         branchdepth: 0,
         branchtable: branchinstructions,
         startloc: start,
         endloc: snes_sim.cpu.get_pc(),
-    });
+    })
 }
 
-pub fn debug_instructions(snes: &Console, start: u32) -> Result<Vec<InstructionWrapper>> {
+pub fn debug_instructions(snes: &mut Console, start: u32) -> Result<Vec<InstructionWrapper>> {
     let mut cycle = 0;
-    let mut state = DebugState::default();
-    state.x = snes.cpu.P.x;
-    state.m = snes.cpu.P.m;
-    state.e = snes.cpu.P.e;
+    let mut state = DebugState {
+        x: snes.cpu.P.x,
+        m: snes.cpu.P.m,
+        e: snes.cpu.P.e,
+    };
     let mut instructions = Vec::<InstructionWrapper>::default();
     let mut temppc = start;
     let mut dbr = snes.cpu.DBR;
-    let mut tempstack = Vec::<DebugState>::new();
     let mut knowninstructions: AHashMap<u32, usize> = AHashMap::new();
     let mut branchinstructions = Vec::<usize>::default();
+    let mut tempsnes = Simulation::new(snes);
     loop {
-        let opcode = memory::peek_byte(snes, temppc)?;
-        let mut tempsnes = snes.clone();
+        let opcode = tempsnes.peek(temppc)?;
         tempsnes.cpu.set_pc(temppc);
         tempsnes.cpu.DBR = dbr;
         tempsnes.cpu.P.x = state.x;
         tempsnes.cpu.P.m = state.m;
         tempsnes.cpu.P.e = state.e;
-        let currinstr = decode_instruction(&tempsnes, opcode, temppc)?;
+        let currinstr = decode_instruction(&mut tempsnes, opcode, temppc)?;
         let branchto = if currinstr.opcode.is_branch() && currinstr.data_addr > start {
             branchinstructions.push(cycle);
             Some(currinstr.data_addr)
@@ -394,7 +314,7 @@ pub fn debug_instructions(snes: &Console, start: u32) -> Result<Vec<InstructionW
             branchfrom: Vec::<u32>::default(),
             branchto,
             data: match currinstr.mode {
-                AddrMode::Immediate => memory::read_word(snes, currinstr.data_addr)?,
+                AddrMode::Immediate => tempsnes.read_word(currinstr.data_addr)?,
                 _ => 0,
             },
             instruction: currinstr.clone(),
@@ -413,25 +333,16 @@ pub fn debug_instructions(snes: &Console, start: u32) -> Result<Vec<InstructionW
             break;
         }
         if currinstr.opcode == OpCode::PLB {
-            dbr = memory::read_byte(&tempsnes, currinstr.data_addr)?
+            dbr = tempsnes.read(currinstr.data_addr)?
         }
         if cycle >= 30 {
             break;
         }
         temppc += currinstr.length(state.m, state.x) as u32;
         cycle += 1;
-        if sets_m(&tempsnes, &tempstack, &currinstr)? {
-            state.m = true
-        }
-        if sets_x(&tempsnes, &tempstack, &currinstr)? {
-            state.x = true
-        }
-        if clears_m(&tempsnes, &tempstack, &currinstr)? {
-            state.m = false
-        }
-        if clears_x(&tempsnes, &tempstack, &currinstr)? {
-            state.x = false
-        }
+        let (set, cleared) = width_changes(&tempsnes, &currinstr)?;
+        state.m = (state.m || set & opcodes::M != 0) && cleared & opcodes::M == 0;
+        state.x = (state.x || set & opcodes::X != 0) && cleared & opcodes::X == 0;
         if clears_e(&tempsnes, &currinstr) {
             state.e = false
         }
@@ -486,12 +397,12 @@ fn loop_to_target(
             false => i += 1,
         };
     }
-    return context;
+    context
 }
 
 pub fn render_wrapped_instructions(mut context: DisassemblerContext) -> DisassemblerContext {
     for branch in context.branchtable.clone() {
-        if let None = context.lines[branch].disassembled.branchto {
+        if context.lines[branch].disassembled.branchto.is_none() {
             continue;
         };
         let target = context.lines[branch].disassembled.branchto.unwrap();
@@ -503,7 +414,7 @@ pub fn render_wrapped_instructions(mut context: DisassemblerContext) -> Disassem
             context.branchdepth = line.flags.len();
         }
     }
-    return context;
+    context
 }
 
 // ╔║╚
@@ -593,17 +504,13 @@ mod tests {
             expanded_header: None,
         };
 
-        let cartridge = cartridge::Cartridge { header, rom_data };
-
-        let ram = vec![0; 0x200000];
+        let cartridge = cartridge::Cartridge {
+            header,
+            rom_data,
+            sram: Vec::new(),
+        };
 
-        Console {
-            cpu: cpu::CPU::new(),
-            cartridge,
-            ram,
-            dma: DMARegisters::default(),
-            mmio: MMIORegisters::default(),
-        }
+        Console::new(cartridge)
     }
 
     #[test]
@@ -661,7 +568,9 @@ mod tests {
                 instruction: cpu::InstructionContext {
                     inst_addr: 0x808000,
                     data_addr: 0x808001,
-                    dest_addr: None,
+                    dest_addr: 0,
+                    crossed_page: false,
+                    byte: 0xA9,
                     opcode: cpu::OpCode::LDA,
                     mode: cpu::AddrMode::Immediate,
                 },
@@ -688,7 +597,9 @@ mod tests {
             instruction: cpu::InstructionContext {
                 inst_addr: 0x808000,
                 data_addr: 0x808001,
-                dest_addr: None,
+                dest_addr: 0,
+                crossed_page: false,
+                byte: 0xA9,
                 opcode: cpu::OpCode::LDA,
                 mode: cpu::AddrMode::Immediate,
             },
@@ -715,7 +626,9 @@ mod tests {
             instruction: cpu::InstructionContext {
                 inst_addr: 0x808000,
                 data_addr: 0x808000,
-                dest_addr: None,
+                dest_addr: 0,
+                crossed_page: false,
+                byte: 0x0A,
                 opcode: cpu::OpCode::ASL,
                 mode: cpu::AddrMode::Accumulator,
             },
@@ -742,7 +655,9 @@ mod tests {
             instruction: cpu::InstructionContext {
                 inst_addr: 0x808000,
                 data_addr: 0x808001,
-                dest_addr: Some(0x808002),
+                dest_addr: 0x808002,
+                crossed_page: false,
+                byte: 0x54,
                 opcode: cpu::OpCode::MVN,
                 mode: cpu::AddrMode::SourceDestination,
             },
@@ -775,7 +690,7 @@ mod tests {
 
         // Use a valid ROM address instead
         console.cpu.set_pc(0x008000);
-        let instructions = debug_instructions(&console, 0x008000)?;
+        let instructions = debug_instructions(&mut console, 0x008000)?;
 
         assert!(!instructions.is_empty());
         assert_eq!(instructions[0].location, 0x008000);
@@ -784,6 +699,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_debug_instructions_follow_sep_rep() -> Result<()> {
+        let mut console = create_test_console();
+        #[rustfmt::skip]
+        let program = [
+            0xE2, 0x30,       // SEP #$30
+            0xA9, 0x12,       // LDA #$12
+            0xC2, 0x10,       // REP #$10
+            0xA2, 0x34, 0x12, // LDX #$1234
+            0xA9, 0x56,       // LDA #$56
+            0x60,             // RTS
+        ];
+        console.cartridge.rom_data[..program.len()].copy_from_slice(&program);
+        console.cpu.P.m = false;
+        console.cpu.P.x = false;
+        console.cpu.P.e = false;
+
+        let instructions = debug_instructions(&mut console, 0x008000)?;
+        let locations: Vec<u32> = instructions.iter().map(|i| i.location).collect();
+        assert_eq!(
+            locations,
+            [0x008000, 0x008002, 0x008004, 0x008006, 0x008009, 0x00800B]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_render_wrapped_instructions_empty() {
         let context = DisassemblerContext::default();
@@ -810,7 +751,9 @@ mod tests {
                 instruction: cpu::InstructionContext {
                     inst_addr: 0x808000,
                     data_addr: 0x808001,
-                    dest_addr: None,
+                    dest_addr: 0,
+                    crossed_page: false,
+                    byte: 0x80,
                     opcode: cpu::OpCode::BRA,
                     mode: cpu::AddrMode::RelativeByte,
                 },
@@ -829,7 +772,9 @@ mod tests {
                 instruction: cpu::InstructionContext {
                     inst_addr: 0x808010,
                     data_addr: 0x808010,
-                    dest_addr: None,
+                    dest_addr: 0,
+                    crossed_page: false,
+                    byte: 0xEA,
                     opcode: cpu::OpCode::NOP,
                     mode: cpu::AddrMode::Implied,
                 },
@@ -856,7 +801,9 @@ mod tests {
             instruction: cpu::InstructionContext {
                 inst_addr: 0x808000,
                 data_addr: 0x808000,
-                dest_addr: None,
+                dest_addr: 0,
+                crossed_page: false,
+                byte: 0xEA,
                 opcode: cpu::OpCode::NOP,
                 mode: cpu::AddrMode::Implied,
             },
@@ -888,7 +835,7 @@ mod tests {
         console.cpu.P.x = false; // 16-bit mode
         console.cpu.P.e = false; // Native mode
 
-        let context = debug_simulation(&console, 10)?;
+        let context = debug_simulation(&mut console, 10)?;
 
         assert!(!context.lines.is_empty());
         assert_eq!(context.startloc, 0x008000);
@@ -897,6 +844,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_debug_simulation_follows_rep() -> Result<()> {
+        let mut console = create_test_console();
+        #[rustfmt::skip]
+        let program = [
+            0xC2, 0x30,       // REP #$30
+            0xA9, 0x34, 0x12, // LDA #$1234
+            0xD0, 0x00,       // BNE +0
+            0xA2, 0x78, 0x56, // LDX #$5678
+            0x60,             // RTS
+        ];
+        console.cartridge.rom_data[..program.len()].copy_from_slice(&program);
+        console.cpu.set_pc(0x008000);
+        console.cpu.P.e = false;
+        console.cpu.P.m = true;
+        console.cpu.P.x = true;
+
+        let context = debug_simulation(&mut console, 10)?;
+        let locations: Vec<u32> = context.lines.iter().map(|line| line.location).collect();
+        assert_eq!(
+            locations,
+            [0x008000, 0x008002, 0x008005, 0x008007, 0x00800A]
+        );
+        // The live CPU keeps its widths
+        assert!(console.cpu.P.m && console.cpu.P.x);
+        Ok(())
+    }
+
     #[test]
     fn test_branch_detection() -> Result<()> {
         let mut console = create_test_console();
@@ -908,7 +883,7 @@ mod tests {
         console.cpu.P.x = false;
         console.cpu.P.e = false;
 
-        let context = debug_simulation(&console, 5)?;
+        let context = debug_simulation(&mut console, 5)?;
 
         // Should detect the branch instruction
         let has_branch = context
@@ -927,18 +902,11 @@ mod tests {
         if std::path::Path::new("./super_metroid.sfc").exists() {
             let cartridge =
                 cartridge::load_rom(std::path::Path::new("./super_metroid.sfc"), false)?;
-            let ram = vec![0; 0x200000];
-            let mut snes = Console {
-                cpu: cpu::CPU::new(),
-                cartridge,
-                ram,
-                dma: DMARegisters::default(),
-                mmio: MMIORegisters::default(),
-            };
+            let mut snes = Console::new(cartridge);
             snes.cpu.P.e = false;
             snes.cpu.set_pc(0x808423);
 
-            let disassembled = debug_simulation(&snes, 100)?;
+            let disassembled = debug_simulation(&mut snes, 100)?;
             let output = render_wrapped_instructions(disassembled);
 
             assert!(!output.lines.is_empty());
@@ -978,7 +946,9 @@ mod tests {
             instruction: cpu::InstructionContext {
                 inst_addr: 0x808000,
                 data_addr: 0x808001,
-                dest_addr: None,
+                dest_addr: 0,
+                crossed_page: false,
+                byte: 0xA9,
                 opcode: cpu::OpCode::LDA,
                 mode: cpu::AddrMode::Immediate,
             },
@@ -1006,7 +976,9 @@ mod tests {
             instruction: cpu::InstructionContext {
                 inst_addr: 0x808000,
                 data_addr: 0x808001,
-                dest_addr: None,
+                dest_addr: 0,
+                crossed_page: false,
+                byte: 0xC2,
                 opcode: cpu::OpCode::REP,
                 mode: cpu::AddrMode::Immediate,
             },
@@ -1032,7 +1004,9 @@ mod tests {
             instruction: cpu::InstructionContext {
                 inst_addr: 0x808000,
                 data_addr: 0x808001,
-                dest_addr: None,
+                dest_addr: 0,
+                crossed_page: false,
+                byte: 0xF4,
                 opcode: cpu::OpCode::PEA,
                 mode: cpu::AddrMode::Immediate,
             },
@@ -0,0 +1,433 @@
+//! Whole ROM disassembler. Follows code from the interrupt vectors, tracking
+//! M, X and E through REP, SEP, PHP/PLP and XCE, and writes asar source where
+//! every byte not reached as code is emitted as data.
+
+use crate::cartridge::{Cartridge, MapMode};
+use crate::cpu::{self, AddrMode, InstructionContext, OpCode};
+use crate::memory;
+use crate::opcodes::{M16, X16};
+use crate::Console;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Register widths and mode at an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    m: bool,
+    x: bool,
+    e: bool,
+    /// Carry, when CLC or SEC set it, for the XCE that usually follows
+    carry: Option<bool>,
+    /// Widths pushed by PHP
+    pushed: Vec<(bool, bool)>,
+}
+
+impl State {
+    fn entry(e: bool) -> State {
+        State {
+            m: true,
+            x: true,
+            e,
+            carry: None,
+            pushed: Vec::new(),
+        }
+    }
+}
+
+struct Decoded {
+    instruction: InstructionContext,
+    /// Length with the widths it was decoded with
+    length: usize,
+}
+
+/// CPU address a ROM offset is written out at. Code may run from a mirror
+/// of it, so labels are keyed by offset instead.
+fn rom_address(map_mode: &MapMode, offset: usize) -> u32 {
+    let offset = offset as u32;
+    match map_mode {
+        // Banks $7E and $7F are WRAM, so the last 64KB of a 4MB image is only
+        // reachable through the $FE and $FF mirrors
+        MapMode::LoROM if offset >= 0x3F0000 => {
+            (offset / 0x8000 + 0x80) << 16 | 0x8000 | (offset % 0x8000)
+        }
+        MapMode::LoROM => (offset / 0x8000) << 16 | 0x8000 | (offset % 0x8000),
+        MapMode::HiROM => 0xC00000 | offset,
+        MapMode::ExHiROM if offset < 0x400000 => 0xC00000 | offset,
+        MapMode::ExHiROM => 0x400000 | (offset - 0x400000),
+    }
+}
+
+/// Bytes per `org` block
+fn block_size(map_mode: &MapMode) -> usize {
+    match map_mode {
+        MapMode::LoROM => 0x8000,
+        MapMode::HiROM | MapMode::ExHiROM => 0x10000,
+    }
+}
+
+struct Disassembler {
+    snes: Console,
+    /// Decoded instructions by ROM offset
+    code: BTreeMap<usize, Decoded>,
+    /// Entry points and targets of branches, jumps and calls, by ROM offset
+    labels: BTreeMap<usize, String>,
+    queue: Vec<(u32, State)>,
+}
+
+impl Disassembler {
+    fn offset(&self, addr: u32) -> Option<usize> {
+        memory::rom_offset(&self.snes, addr)
+            .ok()
+            .filter(|&offset| offset < self.snes.cartridge.rom_data.len())
+    }
+
+    fn add_target(&mut self, addr: u32, state: &State) {
+        if let Some(offset) = self.offset(addr) {
+            let map_mode = &self.snes.cartridge.header.map_mode;
+            let name = format!("CODE_{:06X}", rom_address(map_mode, offset));
+            self.labels.entry(offset).or_insert(name);
+            self.queue.push((addr, state.clone()));
+        }
+    }
+
+    /// Decode straight line code from `addr` until control flow leaves it
+    fn trace(&mut self, mut addr: u32, mut state: State) {
+        while let Some(offset) = self.offset(addr) {
+            if self.code.contains_key(&offset) {
+                return;
+            }
+            let cpu = &mut self.snes.cpu;
+            cpu.set_pc(addr);
+            cpu.P.e = state.e;
+            cpu.P.m = state.m;
+            cpu.P.x = state.x;
+            let Ok(instruction) = cpu::peek_instruction(&self.snes, addr) else {
+                return;
+            };
+            let length = instruction.length(state.m, state.x);
+            if offset + length > self.snes.cartridge.rom_data.len() {
+                return;
+            }
+            let operand = self.snes.cartridge.rom_data[offset + 1..offset + length].to_vec();
+            let opcode = instruction.opcode.clone();
+            let target = instruction.data_addr;
+            let mode = instruction.mode.clone();
+            self.code.insert(
+                offset,
+                Decoded {
+                    instruction,
+                    length,
+                },
+            );
+            // Stay in the bank, as the CPU does
+            addr = addr & 0xFF0000 | (addr as u16).wrapping_add(length as u16) as u32;
+
+            let direct_target = matches!(
+                mode,
+                AddrMode::Absolute
+                    | AddrMode::Long
+                    | AddrMode::RelativeByte
+                    | AddrMode::RelativeWord
+            );
+            match opcode {
+                OpCode::REP => {
+                    state.m &= operand[0] & 0x20 == 0 || state.e;
+                    state.x &= operand[0] & 0x10 == 0 || state.e;
+                }
+                OpCode::SEP => {
+                    state.m |= operand[0] & 0x20 != 0;
+                    state.x |= operand[0] & 0x10 != 0;
+                }
+                OpCode::PHP => state.pushed.push((state.m, state.x)),
+                // Unknown pulls keep the current widths
+                OpCode::PLP => {
+                    if let Some((m, x)) = state.pushed.pop() {
+                        state.m = m;
+                        state.x = x;
+                    }
+                }
+                OpCode::CLC => state.carry = Some(false),
+                OpCode::SEC => state.carry = Some(true),
+                OpCode::XCE => {
+                    let e = state.carry.unwrap_or(false);
+                    state.carry = Some(state.e);
+                    state.e = e;
+                    if e {
+                        state.m = true;
+                        state.x = true;
+                    }
+                }
+                OpCode::JSR | OpCode::JSL if direct_target => self.add_target(target, &state),
+                _ if opcode.is_branch() || opcode == OpCode::BRL => {
+                    self.add_target(target, &state);
+                    if matches!(opcode, OpCode::BRA | OpCode::BRL) {
+                        return;
+                    }
+                }
+                OpCode::JMP | OpCode::JML => {
+                    if direct_target {
+                        self.add_target(target, &state);
+                    }
+                    return;
+                }
+                OpCode::RTS | OpCode::RTL | OpCode::RTI | OpCode::BRK | OpCode::STP => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some((addr, state)) = self.queue.pop() {
+            self.trace(addr, state);
+        }
+    }
+
+    /// Operand text in asar syntax, using labels for code targets. A label
+    /// only stands in for the operand if it assembles to the same bytes, so a
+    /// long address into a mirror bank stays a number.
+    fn operand(&self, offset: usize, decoded: &Decoded, emitted: &BTreeSet<usize>) -> String {
+        let bytes = &self.snes.cartridge.rom_data[offset + 1..offset + decoded.length];
+        let map_mode = &self.snes.cartridge.header.map_mode;
+        let instruction = &decoded.instruction;
+        let encoded = match instruction.mode {
+            AddrMode::Long => 0xFFFFFF,
+            _ => 0xFFFF,
+        };
+        instruction.operand_text(bytes, |target| {
+            let offset = self
+                .offset(target)
+                .filter(|offset| emitted.contains(offset))?;
+            if rom_address(map_mode, offset) & encoded != target & encoded {
+                return None;
+            }
+            self.labels.get(&offset).cloned()
+        })
+    }
+
+    fn line(&self, offset: usize, decoded: &Decoded, emitted: &BTreeSet<usize>) -> String {
+        let instruction = &decoded.instruction;
+        let info = instruction.info();
+        // Size hints keep asar from picking a different width than the ROM
+        let suffix = match instruction.mode {
+            AddrMode::Immediate if info.penalties & (M16 | X16) != 0 => {
+                if decoded.length == 2 {
+                    ".b"
+                } else {
+                    ".w"
+                }
+            }
+            AddrMode::Direct | AddrMode::DirectX | AddrMode::DirectY | AddrMode::Stack => ".b",
+            AddrMode::Absolute | AddrMode::AbsoluteX | AddrMode::AbsoluteY => ".w",
+            AddrMode::Long | AddrMode::LongX => ".l",
+            _ => "",
+        };
+        let suffix = match instruction.opcode {
+            OpCode::PEI | OpCode::JSR | OpCode::JMP | OpCode::JSL | OpCode::JML => "",
+            _ => suffix,
+        };
+        let mnemonic = match (&instruction.opcode, &instruction.mode) {
+            (OpCode::JMP, AddrMode::Long) => "jml".to_string(),
+            (opcode, _) => opcode.to_string().to_lowercase(),
+        };
+        let operand = self.operand(offset, decoded, emitted);
+        let mut line = format!("    {}{}", mnemonic, suffix);
+        if !operand.is_empty() {
+            line.push(' ');
+            line.push_str(&operand);
+        }
+        line
+    }
+
+    fn render(&self) -> String {
+        let rom = &self.snes.cartridge.rom_data;
+        let map_mode = &self.snes.cartridge.header.map_mode;
+        let block = block_size(map_mode);
+
+        // Instructions overlapped by an earlier one can't be written out
+        let mut starts = BTreeSet::new();
+        let mut next = 0;
+        for (&offset, decoded) in &self.code {
+            if offset >= next && (offset % block) + decoded.length <= block {
+                starts.insert(offset);
+                next = offset + decoded.length;
+            }
+        }
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "; {}\n{}\n",
+            self.snes.cartridge.header.title.trim(),
+            match map_mode {
+                MapMode::LoROM => "lorom",
+                MapMode::HiROM => "hirom",
+                MapMode::ExHiROM => "exhirom",
+            }
+        );
+        let mut offset = 0;
+        let mut data: Vec<u8> = Vec::new();
+        let flush = |out: &mut String, data: &mut Vec<u8>| {
+            for chunk in data.chunks(16) {
+                let bytes: Vec<String> = chunk.iter().map(|b| format!("${:02X}", b)).collect();
+                let _ = writeln!(out, "    db {}", bytes.join(","));
+            }
+            data.clear();
+        };
+        while offset < rom.len() {
+            let addr = rom_address(map_mode, offset);
+            if offset % block == 0 {
+                flush(&mut out, &mut data);
+                let _ = writeln!(out, "\norg ${:06X}", addr);
+            }
+            if starts.contains(&offset) {
+                flush(&mut out, &mut data);
+                if let Some(label) = self.labels.get(&offset) {
+                    let _ = writeln!(out, "{}:", label);
+                }
+                let decoded = &self.code[&offset];
+                let _ = writeln!(out, "{}", self.line(offset, decoded, &starts));
+                offset += decoded.length;
+            } else {
+                data.push(rom[offset]);
+                offset += 1;
+            }
+        }
+        flush(&mut out, &mut data);
+        out
+    }
+}
+
+/// Disassemble a whole cartridge to asar source
+pub fn disassemble_rom(cartridge: Cartridge) -> String {
+    let vectors = cartridge.header.interrupt_vectors.clone();
+    let mut disassembler = Disassembler {
+        snes: Console::new(cartridge),
+        code: BTreeMap::new(),
+        labels: BTreeMap::new(),
+        queue: Vec::new(),
+    };
+    let entries = [
+        ("Reset", vectors.reset, true),
+        ("NMI_Emu", vectors.nmi_emu, true),
+        ("IRQ_Emu", vectors.irq_emu, true),
+        ("COP_Emu", vectors.cop_emu, true),
+        ("ABORT_Emu", vectors.abort_emu, true),
+        ("NMI", vectors.nmi, false),
+        ("IRQ", vectors.irq, false),
+        ("BRK", vectors.brk, false),
+        ("COP", vectors.cop, false),
+        ("ABORT", vectors.abort, false),
+    ];
+    // Trace in reverse so the reset code is decoded first
+    for (name, vector, e) in entries.into_iter().rev() {
+        let addr = vector as u32;
+        if let Some(offset) = disassembler.offset(addr) {
+            disassembler
+                .labels
+                .entry(offset)
+                .or_insert(name.to_string());
+            disassembler.queue.push((addr, State::entry(e)));
+        }
+    }
+    disassembler.run();
+    disassembler.render()
+}
+
+#[cfg(test)]
+mod disasm_tests {
+    use super::*;
+    use crate::single_step::flat_console;
+
+    #[test]
+    fn lorom_addresses_skip_wram_banks() {
+        assert_eq!(rom_address(&MapMode::LoROM, 0x000000), 0x008000);
+        assert_eq!(rom_address(&MapMode::LoROM, 0x3EFFFF), 0x7DFFFF);
+        assert_eq!(rom_address(&MapMode::LoROM, 0x3F0000), 0xFE8000);
+        assert_eq!(rom_address(&MapMode::LoROM, 0x3FFFFF), 0xFFFFFF);
+    }
+
+    #[test]
+    fn disassembles_reachable_code() {
+        let mut cartridge = flat_console().cartridge;
+        #[rustfmt::skip]
+        let program = [
+            0x18,             // $8000 CLC
+            0xFB,             // $8001 XCE
+            0xC2, 0x30,       // $8002 REP #$30
+            0xA9, 0x34, 0x12, // $8004 LDA #$1234
+            0x20, 0x10, 0x80, // $8007 JSR $8010
+            0x80, 0xF8,       // $800A BRA $8004
+            0x40,             // $800C RTI
+            0x01, 0x02, 0x03, // $800D data
+            0xE8,             // $8010 INX
+            0x60,             // $8011 RTS
+        ];
+        let mut rom = vec![0xFF; 0x8000];
+        rom[..program.len()].copy_from_slice(&program);
+        cartridge.rom_data = rom;
+        cartridge.header.rom_size = 0x8000;
+        cartridge.header.interrupt_vectors.reset = 0x8000;
+        cartridge.header.interrupt_vectors.nmi = 0x800C;
+
+        let asm = disassemble_rom(cartridge);
+        assert!(asm.contains("lorom"));
+        assert!(asm.contains("org $008000"));
+        assert!(asm.contains("Reset:"));
+        assert!(asm.contains("NMI:"));
+        assert!(asm.contains("lda.w #$1234"));
+        assert!(asm.contains("jsr CODE_008010"));
+        assert!(asm.contains("bra CODE_008004"));
+        assert!(asm.contains("CODE_008010:"));
+        assert!(asm.contains("db $01,$02,$03"));
+        assert!(asm.contains("db $FF,$FF"));
+    }
+
+    #[test]
+    fn hirom_labels_follow_bank_zero_code() {
+        let mut cartridge = flat_console().cartridge;
+        #[rustfmt::skip]
+        let program = [
+            0x22, 0x10, 0x80, 0xC0, // $8000 JSL $C08010
+            0x22, 0x10, 0x80, 0x00, // $8004 JSL $008010
+            0x80, 0xF6,             // $8008 BRA $8000
+        ];
+        let mut rom = vec![0xFF; 0x10000];
+        rom[0x8000..0x8000 + program.len()].copy_from_slice(&program);
+        rom[0x8010] = 0x6B; // RTL
+        cartridge.rom_data = rom;
+        cartridge.header.map_mode = MapMode::HiROM;
+        cartridge.header.rom_size = 0x10000;
+        cartridge.header.interrupt_vectors.reset = 0x8000;
+
+        let asm = disassemble_rom(cartridge);
+        assert!(asm.contains("hirom"));
+        assert!(asm.contains("org $C00000"));
+        assert!(asm.contains("Reset:\n    jsl CODE_C08010"));
+        // A label would assemble to $C08010, not the bank zero mirror
+        assert!(asm.contains("jsl $008010"));
+        assert!(asm.contains("bra Reset"));
+        assert!(asm.contains("CODE_C08010:\n    rtl"));
+    }
+
+    #[test]
+    fn lorom_labels_follow_fastrom_mirror() {
+        let mut cartridge = flat_console().cartridge;
+        #[rustfmt::skip]
+        let program = [
+            0x5C, 0x04, 0x80, 0x80, // $8000 JML $808004
+            0xEA,                   // $808004 NOP
+            0xD0, 0xFD,             // $808005 BNE $808004
+            0x60,                   // $808007 RTS
+        ];
+        let mut rom = vec![0xFF; 0x8000];
+        rom[..program.len()].copy_from_slice(&program);
+        cartridge.rom_data = rom;
+        cartridge.header.rom_size = 0x8000;
+        cartridge.header.interrupt_vectors.reset = 0x8000;
+
+        let asm = disassemble_rom(cartridge);
+        assert!(asm.contains("jml $808004"));
+        assert!(asm.contains("CODE_008004:\n    nop"));
+        assert!(asm.contains("bne CODE_008004"));
+        assert!(asm.contains("    rts"));
+    }
+}
@@ -0,0 +1,279 @@
+//! Callbacks for tools that watch the core run, such as profilers, coverage
+//! and cheats. Hooks see CPU reads and writes through `memory::Bus` only,
+//! never debugger peeks. Accesses made from inside a hook don't fire hooks
+//! again.
+
+use crate::cpu::{InstructionContext, Interrupt};
+use crate::Console;
+use std::sync::{Mutex, PoisonError};
+
+pub type ExecuteHook = Box<dyn FnMut(&Console, &InstructionContext) + Send>;
+pub type MemoryHook = Box<dyn FnMut(MemoryAccess) + Send>;
+pub type InterruptHook = Box<dyn FnMut(&Console, Interrupt) + Send>;
+
+/// A CPU read or write that reached the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub addr: u32,
+    pub data: u16,
+    /// Word access, with the high byte at `addr + 1`
+    pub word: bool,
+}
+
+#[derive(Default)]
+struct HookLists {
+    pre_execute: Vec<ExecuteHook>,
+    post_execute: Vec<ExecuteHook>,
+    read: Vec<MemoryHook>,
+    write: Vec<MemoryHook>,
+    interrupt: Vec<InterruptHook>,
+}
+
+/// Hooks registered on a `Console`. Firing is a single branch while nothing
+/// is registered. Clones start with no hooks, so simulating on a copy of the
+/// console stays invisible to tools.
+#[derive(Default)]
+pub struct Hooks {
+    lists: Mutex<HookLists>,
+    active: bool,
+}
+
+impl Clone for Hooks {
+    fn clone(&self) -> Hooks {
+        Hooks::default()
+    }
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.lists.try_lock() {
+            Ok(lists) => f
+                .debug_struct("Hooks")
+                .field("pre_execute", &lists.pre_execute.len())
+                .field("post_execute", &lists.post_execute.len())
+                .field("read", &lists.read.len())
+                .field("write", &lists.write.len())
+                .field("interrupt", &lists.interrupt.len())
+                .finish(),
+            Err(_) => f.write_str("Hooks { .. }"),
+        }
+    }
+}
+
+impl Hooks {
+    #[inline]
+    pub fn pre_execute(&self, snes: &Console, instruction: &InstructionContext) {
+        if self.active {
+            self.fire(|lists| {
+                for hook in &mut lists.pre_execute {
+                    hook(snes, instruction);
+                }
+            });
+        }
+    }
+
+    #[inline]
+    pub fn post_execute(&self, snes: &Console, instruction: &InstructionContext) {
+        if self.active {
+            self.fire(|lists| {
+                for hook in &mut lists.post_execute {
+                    hook(snes, instruction);
+                }
+            });
+        }
+    }
+
+    #[inline]
+    pub fn read(&self, addr: u32, data: u16, word: bool) {
+        if self.active {
+            let access = MemoryAccess { addr, data, word };
+            self.fire(|lists| {
+                for hook in &mut lists.read {
+                    hook(access);
+                }
+            });
+        }
+    }
+
+    #[inline]
+    pub fn write(&self, addr: u32, data: u16, word: bool) {
+        if self.active {
+            let access = MemoryAccess { addr, data, word };
+            self.fire(|lists| {
+                for hook in &mut lists.write {
+                    hook(access);
+                }
+            });
+        }
+    }
+
+    #[inline]
+    pub fn interrupt(&self, snes: &Console, kind: Interrupt) {
+        if self.active {
+            self.fire(|lists| {
+                for hook in &mut lists.interrupt {
+                    hook(snes, kind);
+                }
+            });
+        }
+    }
+
+    /// Run hooks unless we are already inside one
+    fn fire(&self, f: impl FnOnce(&mut HookLists)) {
+        if let Ok(mut lists) = self.lists.try_lock() {
+            f(&mut lists);
+        }
+    }
+
+    fn register(&mut self, f: impl FnOnce(&mut HookLists)) {
+        f(self.lists.get_mut().unwrap_or_else(PoisonError::into_inner));
+        self.active = true;
+    }
+}
+
+impl Console {
+    /// Called before each instruction executes
+    pub fn on_pre_execute(
+        &mut self,
+        hook: impl FnMut(&Console, &InstructionContext) + Send + 'static,
+    ) {
+        self.hooks
+            .register(|lists| lists.pre_execute.push(Box::new(hook)));
+    }
+
+    /// Called after each instruction executes
+    pub fn on_post_execute(
+        &mut self,
+        hook: impl FnMut(&Console, &InstructionContext) + Send + 'static,
+    ) {
+        self.hooks
+            .register(|lists| lists.post_execute.push(Box::new(hook)));
+    }
+
+    pub fn on_read(&mut self, hook: impl FnMut(MemoryAccess) + Send + 'static) {
+        self.hooks.register(|lists| lists.read.push(Box::new(hook)));
+    }
+
+    pub fn on_write(&mut self, hook: impl FnMut(MemoryAccess) + Send + 'static) {
+        self.hooks
+            .register(|lists| lists.write.push(Box::new(hook)));
+    }
+
+    /// Called on entry to any interrupt, including BRK and COP
+    pub fn on_interrupt(&mut self, hook: impl FnMut(&Console, Interrupt) + Send + 'static) {
+        self.hooks
+            .register(|lists| lists.interrupt.push(Box::new(hook)));
+    }
+
+    /// Remove every registered hook
+    pub fn clear_hooks(&mut self) {
+        self.hooks = Hooks::default();
+    }
+}
+
+#[cfg(test)]
+mod hooks_tests {
+    use super::*;
+    use crate::cpu;
+    use crate::memory::Bus;
+    use crate::single_step::flat_console;
+    use std::sync::Arc;
+
+    type Log<T> = Arc<Mutex<Vec<T>>>;
+
+    fn recorder<T>() -> (Log<T>, Log<T>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        (log.clone(), log)
+    }
+
+    #[test]
+    fn memory_hooks_see_reads_and_writes() {
+        let mut snes = flat_console();
+        snes.cpu.PC = 0x1000;
+        snes.cpu.P.m = true;
+        // LDA $2000; STA $2001
+        snes.ram[0x1000..0x1006].copy_from_slice(&[0xAD, 0x00, 0x20, 0x8D, 0x01, 0x20]);
+        snes.ram[0x2000] = 0x42;
+        let (reads, log) = recorder();
+        snes.on_read(move |access| log.lock().unwrap().push(access));
+        let (writes, log) = recorder();
+        snes.on_write(move |access| log.lock().unwrap().push(access));
+
+        cpu::step(&mut snes).unwrap();
+        cpu::step(&mut snes).unwrap();
+        let read = MemoryAccess {
+            addr: 0x002000,
+            data: 0x42,
+            word: false,
+        };
+        assert!(reads.lock().unwrap().contains(&read));
+        // Opcode and operand fetches are reads too
+        let addrs: Vec<u32> = reads.lock().unwrap().iter().map(|a| a.addr).collect();
+        assert_eq!(
+            addrs,
+            vec![0x001000, 0x001001, 0x001002, 0x002000, 0x001003, 0x001004, 0x001005]
+        );
+        // The store was the last thing on the data bus
+        assert_eq!(snes.mdr, 0x42);
+        let write = MemoryAccess {
+            addr: 0x002001,
+            data: 0x42,
+            word: false,
+        };
+        assert_eq!(*writes.lock().unwrap(), vec![write]);
+
+        // Peeks stay invisible
+        let count = reads.lock().unwrap().len();
+        snes.peek(0x002000).unwrap();
+        snes.peek_word(0x002000).unwrap();
+        assert_eq!(reads.lock().unwrap().len(), count);
+    }
+
+    #[test]
+    fn execute_hooks_bracket_instructions() {
+        let mut snes = flat_console();
+        snes.cpu.PC = 0x1000;
+        // INX
+        snes.ram[0x1000] = 0xE8;
+        let (events, log) = recorder();
+        snes.on_pre_execute(move |snes, instruction| {
+            log.lock()
+                .unwrap()
+                .push(("pre", instruction.inst_addr, snes.cpu.X))
+        });
+        let log = events.clone();
+        snes.on_post_execute(move |snes, instruction| {
+            log.lock()
+                .unwrap()
+                .push(("post", instruction.inst_addr, snes.cpu.X))
+        });
+        cpu::step(&mut snes).unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![("pre", 0x001000, 0), ("post", 0x001000, 1)]
+        );
+    }
+
+    #[test]
+    fn interrupt_hook_and_clones() {
+        let mut snes = flat_console();
+        snes.cpu.PC = 0x1000;
+        let (interrupts, log) = recorder();
+        snes.on_interrupt(move |_, kind| log.lock().unwrap().push(kind));
+
+        // Simulating on a copy fires nothing
+        let mut copy = snes.clone();
+        copy.cpu.nmi_pending = true;
+        cpu::step(&mut copy).unwrap();
+        assert!(interrupts.lock().unwrap().is_empty());
+
+        snes.cpu.nmi_pending = true;
+        cpu::step(&mut snes).unwrap();
+        assert_eq!(*interrupts.lock().unwrap(), vec![Interrupt::NMI]);
+
+        snes.clear_hooks();
+        snes.cpu.nmi_pending = true;
+        cpu::step(&mut snes).unwrap();
+        assert_eq!(interrupts.lock().unwrap().len(), 1);
+    }
+}
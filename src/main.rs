@@ -1,18 +1,32 @@
 #![allow(unused_variables, dead_code, unused_mut)]
 
+mod alu;
+mod assembler;
 mod cartridge;
 mod cpu;
 mod debugger;
+mod disasm;
+mod hooks;
 mod memory;
+mod memory_map;
+mod opcodes;
 mod registers;
+mod simulation;
+#[cfg(test)]
+mod single_step;
+mod trace;
 
+use ahash::AHashMap;
 use cartridge::*;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use color_eyre::Result;
 use cpu::*;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use debugger::{debug_simulation, render_wrapped_instructions, DisassemblerContext, Flag};
+use hooks::Hooks;
 use log::{error, trace};
+use memory::Bus;
+use memory_map::MemoryMap;
 use pretty_env_logger::env_logger::fmt::Target;
 use ratatui::{
     layout::Constraint,
@@ -21,9 +35,13 @@ use ratatui::{
     widgets::{block::*, *},
 };
 use registers::*;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::{time::Duration, time::Instant};
 use symbols::scrollbar;
+use trace::TraceLogger;
 use tui_input::backend::crossterm::EventHandler;
 use tui_input::Input;
 
@@ -31,10 +49,14 @@ use crate::debugger::DisassemblerError;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about=None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// sfc format ROM file
-    #[arg(index = 1)]
-    rom: PathBuf,
+    #[arg(index = 1, required = true)]
+    rom: Option<PathBuf>,
 
     /// Bypass checksum verification
     #[arg(short, long, default_value_t = false)]
@@ -43,6 +65,40 @@ struct Args {
     /// Disable TUI mode
     #[arg(short, long, default_value_t = true)]
     tui: bool,
+
+    /// Write a bsnes style instruction trace to this file
+    #[arg(long)]
+    trace: Option<PathBuf>,
+
+    /// Run headless against a reference trace and stop at the first mismatch
+    #[arg(long)]
+    compare: Option<PathBuf>,
+
+    /// Instructions to show before a trace mismatch
+    #[arg(long, default_value_t = 20)]
+    history: usize,
+
+    /// Fail on reads from unmapped addresses and write-only registers
+    /// instead of returning open bus
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Disassemble a whole ROM to asar source
+    Disasm {
+        /// sfc format ROM file
+        rom: PathBuf,
+
+        /// Output file. Defaults to the ROM path with an .asm extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Bypass checksum verification
+        #[arg(short, long, default_value_t = false)]
+        checksum: bool,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -77,9 +133,45 @@ pub struct App {
 pub struct Console {
     cpu: CPU,
     cartridge: Cartridge,
+    /// Built from the cartridge's map mode
+    map: MemoryMap,
     ram: Vec<u8>,
     mmio: MMIORegisters,
     dma: DMARegisters,
+    /// Master clock cycles elapsed since power on
+    master_clock: u64,
+    /// Treat `ram` as one flat 16 MB address space with no ROM or I/O. Used
+    /// to run CPU test vectors.
+    flat_memory: bool,
+    hooks: Hooks,
+    /// RAM and SRAM writes made while simulating. See
+    /// `simulation::Simulation`.
+    overlay: Option<AHashMap<memory::Target, u8>>,
+    /// Memory data register: the last value on the data bus, which is what
+    /// open bus reads return
+    mdr: u8,
+    /// Report open bus reads as errors. Useful for finding emulator bugs.
+    strict_bus: bool,
+}
+
+impl Console {
+    /// A console at power on with `cartridge` inserted
+    pub fn new(cartridge: Cartridge) -> Console {
+        Console {
+            cpu: CPU::new(),
+            map: MemoryMap::new(&cartridge.header.map_mode),
+            cartridge,
+            ram: vec![0; 0x20000],
+            mmio: MMIORegisters::default(),
+            dma: DMARegisters::default(),
+            master_clock: 0,
+            flat_memory: false,
+            hooks: Hooks::default(),
+            overlay: None,
+            mdr: 0,
+            strict_bus: false,
+        }
+    }
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -290,6 +382,10 @@ fn ui(f: &mut Frame, app: &mut App, snes: &Console) {
 enum DebuggerCommand {
     Breakpoint(u32),
     NMI,
+    /// Code was changed, so the disassembly is stale
+    Patched,
+    /// Flush battery backed SRAM to the save file
+    SaveSram,
     Default,
 }
 
@@ -307,6 +403,16 @@ fn execute_command(command: &str, snes: &mut Console) -> Result<DebuggerCommand>
         }
         "b" => DebuggerCommand::Breakpoint(u32::from_str_radix(commandparts[1], 16)?),
         "nmi" => DebuggerCommand::NMI,
+        "save" => DebuggerCommand::SaveSram,
+        "a" => {
+            let addr = u32::from_str_radix(commandparts[1], 16)?;
+            let line = commandparts[2..].join(" ");
+            let bytes = assembler::assemble(&line, addr, snes.cpu.P.m, snes.cpu.P.x)?;
+            for (i, byte) in bytes.into_iter().enumerate() {
+                memory::poke_byte(snes, addr + i as u32, byte)?;
+            }
+            DebuggerCommand::Patched
+        }
         "p" => {
             snes.cpu.set_p(u8::from_str_radix(commandparts[1], 2)?);
             DebuggerCommand::Default
@@ -315,7 +421,18 @@ fn execute_command(command: &str, snes: &mut Console) -> Result<DebuggerCommand>
     })
 }
 
-fn main() -> Result<()> {
+/// Step the CPU, through the trace logger when tracing is enabled
+fn step(
+    snes: &mut Console,
+    tracer: &mut Option<TraceLogger<BufWriter<File>>>,
+) -> Result<(CPUExecutionResult, u8)> {
+    match tracer {
+        Some(tracer) => tracer.step(snes),
+        None => Ok(cpu::step(snes)?),
+    }
+}
+
+fn main() -> Result<ExitCode> {
     color_eyre::install()?;
     pretty_env_logger::formatted_builder()
         .target(Target::Stderr)
@@ -323,25 +440,75 @@ fn main() -> Result<()> {
 
     let args = Args::parse();
 
-    let (mut terminal, tui) = if args.tui {
+    if let Some(Command::Disasm {
+        rom,
+        output,
+        checksum,
+    }) = &args.command
+    {
+        let cartridge = load_rom(rom, *checksum)?;
+        let output = output.clone().unwrap_or_else(|| rom.with_extension("asm"));
+        std::fs::write(&output, disasm::disassemble_rom(cartridge))?;
+        println!("Wrote {}", output.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+    let rom = args
+        .rom
+        .as_ref()
+        .expect("clap requires a ROM without a subcommand");
+
+    let (mut terminal, tui) = if args.tui && args.compare.is_none() {
         (Some(ratatui::init()), true)
     } else {
         (None, false)
     };
 
-    let cartridge = load_rom(&args.rom, args.checksum)?;
-
-    let mut ram = vec![0; 0x200000];
+    let mut cartridge = load_rom(rom, args.checksum)?;
+    let srm_file = sram_path(rom);
+    load_sram(&mut cartridge, &srm_file)?;
 
-    let mut snes = Console {
-        cpu: CPU::new(),
-        cartridge,
-        ram,
-        mmio: MMIORegisters::default(),
-        dma: DMARegisters::default(),
+    let mut tracer = match &args.trace {
+        Some(path) => Some(TraceLogger::new(BufWriter::new(File::create(path)?))),
+        None => None,
     };
+
+    let mut snes = Console::new(cartridge);
+    snes.strict_bus = args.strict;
     snes.cpu.PC = snes.cartridge.header.interrupt_vectors.reset;
-    // let op = memory::read_byte(&snes, snes.cpu.get_pc())?;
+
+    // Every way out of the run, errors included, comes back here to save
+    let result = run(&mut snes, &args, terminal, &mut tracer, &srm_file);
+    if tui {
+        ratatui::restore();
+    }
+    let saved = save_sram(&snes.cartridge, &srm_file);
+    let code = result?;
+    saved?;
+    Ok(code)
+}
+
+/// Run the console until the debugger quits, the reference trace is done or
+/// an instruction fails
+fn run(
+    snes: &mut Console,
+    args: &Args,
+    mut terminal: Option<ratatui::DefaultTerminal>,
+    tracer: &mut Option<TraceLogger<BufWriter<File>>>,
+    srm_file: &Path,
+) -> Result<ExitCode> {
+    let tui = terminal.is_some();
+    if let Some(path) = &args.compare {
+        let reference = BufReader::new(File::open(path)?);
+        match trace::compare(snes, reference, args.history)? {
+            Some(divergence) => {
+                println!("{}", divergence);
+                return Ok(ExitCode::FAILURE);
+            }
+            None => println!("Matched reference trace"),
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    // let op = snes.read(snes.cpu.get_pc())?;
     // let instr = cpu::decode_instruction(&snes, op)?;
     // cpu::execute_instruction(&mut snes, &instr)?;
 
@@ -354,17 +521,13 @@ fn main() -> Result<()> {
     let mut last_tick = Instant::now();
     'mainloop: loop {
         if !tui {
-            let op = memory::read_byte(&snes, snes.cpu.get_pc())?;
-            let instr = cpu::decode_instruction(&snes, op, snes.cpu.get_pc())?;
-            cpu::execute_instruction(&mut snes, &instr)?;
+            step(snes, tracer)?;
             // let mut trash: String = String::default();
             // io::stdin().read_line(&mut trash)?;
             trace!("Next");
         } else {
             if app.run {
-                let op = memory::read_byte(&snes, snes.cpu.get_pc())?;
-                let instr = cpu::decode_instruction(&snes, op, snes.cpu.get_pc())?;
-                let res = cpu::execute_instruction(&mut snes, &instr)?;
+                step(snes, tracer)?;
                 app.current_pc = snes.cpu.get_pc();
                 if app.current_pc == app.breakpoint {
                     app.run = false;
@@ -374,7 +537,7 @@ fn main() -> Result<()> {
             if app.disassembler_ptr >= app.disassembled.lines.len()
                 || app.disassembled.lines.is_empty()
             {
-                let temp = match debug_simulation(&snes, 100) {
+                let temp = match debug_simulation(snes, 100) {
                     Ok(r) => r,
                     Err(e) => {
                         ratatui::restore();
@@ -385,6 +548,7 @@ fn main() -> Result<()> {
                                 return Err(de.source);
                             }
                             DisassemblerError::Other(e) => return Err(e),
+                            e => return Err(e.into()),
                         }
                     }
                 };
@@ -393,10 +557,7 @@ fn main() -> Result<()> {
                 app.disassembled = render_wrapped_instructions(temp);
                 app.disassembler_ptr = 0;
             }
-            terminal
-                .as_mut()
-                .unwrap()
-                .draw(|f| ui(f, &mut app, &snes))?;
+            terminal.as_mut().unwrap().draw(|f| ui(f, &mut app, snes))?;
             let timeout: Duration = tick_rate.saturating_sub(last_tick.elapsed());
             if crossterm::event::poll(timeout)? {
                 if let Event::Key(key) = event::read()? {
@@ -424,9 +585,7 @@ fn main() -> Result<()> {
                             }
                             KeyCode::Char('n') => {
                                 trace!("Next");
-                                let op = memory::read_byte(&snes, snes.cpu.get_pc())?;
-                                let instr = cpu::decode_instruction(&snes, op, snes.cpu.get_pc())?;
-                                let res = cpu::execute_instruction(&mut snes, &instr)?;
+                                let (res, _) = step(snes, tracer)?;
                                 app.branch_taken =
                                     matches!(res, cpu::CPUExecutionResult::BranchTaken);
                                 if app.branch_taken
@@ -454,7 +613,7 @@ fn main() -> Result<()> {
                                 app.current_pc = snes.cpu.get_pc();
                             }
                             KeyCode::Char('r') => {
-                                let temp = match debug_simulation(&snes, 100) {
+                                let temp = match debug_simulation(snes, 100) {
                                     Ok(r) => r,
                                     Err(e) => {
                                         ratatui::restore();
@@ -465,6 +624,7 @@ fn main() -> Result<()> {
                                                 return Err(de.source);
                                             }
                                             DisassemblerError::Other(e) => return Err(e),
+                                            e => return Err(e.into()),
                                         }
                                     }
                                 };
@@ -478,16 +638,24 @@ fn main() -> Result<()> {
                         InputMode::Edit => match key.code {
                             KeyCode::Esc => app.input_mode = InputMode::Normal,
                             KeyCode::Enter => {
-                                let cmd = execute_command(app.input.value(), &mut snes)?;
+                                let cmd = execute_command(app.input.value(), snes)?;
                                 match cmd {
                                     DebuggerCommand::Breakpoint(addr) => {
                                         app.breakpoint = addr;
                                         app.breakpoint_set = true;
                                     }
                                     DebuggerCommand::NMI => {
-                                        cpu::execute_nmi(&mut snes)?;
+                                        snes.cpu.nmi_pending = true;
+                                        step(snes, tracer)?;
                                         app.disassembler_ptr = app.disassembled.lines.len()
                                     }
+                                    DebuggerCommand::Patched => {
+                                        app.disassembled = DisassemblerContext::default();
+                                        app.disassembler_ptr = 0;
+                                    }
+                                    DebuggerCommand::SaveSram => {
+                                        save_sram(&snes.cartridge, srm_file)?
+                                    }
                                     DebuggerCommand::Default => {}
                                 }
                                 app.input.reset();
@@ -506,7 +674,5 @@ fn main() -> Result<()> {
         }
     }
 
-    ratatui::restore();
-
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,182 @@
+//! Page table for the 24 bit CPU address space, built once per cartridge.
+//! Each 8 KB page points straight at ROM, WRAM, SRAM or the I/O handlers, so a bus
+//! access is one lookup instead of a chain of bank and offset checks.
+
+use crate::cartridge::MapMode;
+
+pub const PAGE_SIZE: u32 = 0x2000;
+pub const PAGE_MASK: u32 = PAGE_SIZE - 1;
+const PAGES: usize = 0x1000000 / PAGE_SIZE as usize;
+
+/// What an 8 KB page of the address space is wired to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    /// ROM, starting at this offset in the image
+    Rom(usize),
+    /// WRAM, starting at this offset in `Console::ram`
+    Ram(usize),
+    /// Cartridge SRAM, starting at this offset. Wraps at the SRAM size, and is
+    /// unmapped on boards without any
+    Sram(usize),
+    /// $2000-$7FFF of a system bank: PPU, CPU and DMA registers
+    Io,
+    Unmapped,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryMap {
+    pages: Box<[Page]>,
+}
+
+impl MemoryMap {
+    pub fn new(map_mode: &MapMode) -> MemoryMap {
+        let pages = (0..PAGES)
+            .map(|page| Self::decode(map_mode, page as u32 * PAGE_SIZE))
+            .collect();
+        MemoryMap { pages }
+    }
+
+    #[inline]
+    pub fn page(&self, addr: u32) -> Page {
+        self.pages[(addr & 0xFFFFFF) as usize / PAGE_SIZE as usize]
+    }
+
+    /// Where the page starting at `addr` lives
+    fn decode(map_mode: &MapMode, addr: u32) -> Page {
+        let bank = addr >> 16;
+        let addr_word = addr & 0xFFFF;
+        let system_bank = bank % 0x80 < 0x40;
+        match bank {
+            0x7E | 0x7F => Page::Ram((addr & 0x1FFFF) as usize),
+            // The first 8 KB of WRAM shows up in every system bank
+            _ if system_bank && addr_word < 0x2000 => Page::Ram(addr_word as usize),
+            _ => match Self::sram_offset(map_mode, addr) {
+                Some(offset) => Page::Sram(offset),
+                None if system_bank && addr_word < 0x8000 => Page::Io,
+                None => Page::Rom(Self::rom_offset(map_mode, addr)),
+            },
+        }
+    }
+
+    /// Offset into SRAM for `addr`, if the map mode puts SRAM there
+    fn sram_offset(map_mode: &MapMode, addr: u32) -> Option<usize> {
+        let bank = addr >> 16;
+        let addr_word = addr & 0xFFFF;
+        let high_window = (0x6000..0x8000).contains(&addr_word);
+        let offset = match map_mode {
+            // $70-$7D and $F0-$FF, $0000-$7FFF
+            MapMode::LoROM
+                if ((0x70..0x7E).contains(&bank) || bank >= 0xF0) && addr_word < 0x8000 =>
+            {
+                (bank & 0xF) * 0x8000 + addr_word
+            }
+            // $20-$3F and $A0-$BF, $6000-$7FFF
+            MapMode::HiROM if (0x20..0x40).contains(&(bank % 0x80)) && high_window => {
+                (bank & 0x1F) * 0x2000 + (addr_word & 0x1FFF)
+            }
+            // $80-$BF, $6000-$7FFF
+            MapMode::ExHiROM if (0x80..0xC0).contains(&bank) && high_window => {
+                (bank & 0x3F) * 0x2000 + (addr_word & 0x1FFF)
+            }
+            _ => return None,
+        };
+        Some(offset as usize)
+    }
+
+    fn rom_offset(map_mode: &MapMode, addr: u32) -> usize {
+        match map_mode {
+            // The low half of banks $40+ mirrors the high half
+            MapMode::LoROM => (((addr >> 16) % 0x80) * 0x8000 + (addr & 0x7FFF)) as usize,
+            MapMode::HiROM => (addr & 0x3FFFFF) as usize,
+            MapMode::ExHiROM => {
+                ((addr & 0x3FFFFF) + (((addr & 0x800000) ^ 0x800000) >> 1)) as usize
+            }
+        }
+    }
+}
+
+/// Fold an offset past the end of a chip back into it the way cartridge
+/// boards do. A power of two size simply repeats. Otherwise the chip splits
+/// into power of two parts, and the space above each part mirrors the one
+/// below it, so a 3 MB ROM reads $200000-$2FFFFF again at $300000.
+pub fn mirror(mut offset: usize, mut size: usize) -> usize {
+    if size == 0 {
+        return 0;
+    }
+    let mut base = 0;
+    let mut mask = 1 << 23;
+    while offset >= size {
+        while offset & mask == 0 {
+            mask >>= 1;
+        }
+        offset -= mask;
+        if size > mask {
+            size -= mask;
+            base += mask;
+        }
+        mask >>= 1;
+    }
+    base + offset
+}
+
+#[cfg(test)]
+mod memory_map_tests {
+    use super::*;
+
+    #[test]
+    fn lorom_pages() {
+        let map = MemoryMap::new(&MapMode::LoROM);
+        assert_eq!(map.page(0x000000), Page::Ram(0));
+        assert_eq!(map.page(0x010000), Page::Ram(0));
+        assert_eq!(map.page(0xBF0000), Page::Ram(0));
+        assert_eq!(map.page(0x7E2000), Page::Ram(0x2000));
+        assert_eq!(map.page(0x7FE000), Page::Ram(0x1E000));
+        assert_eq!(map.page(0x002100), Page::Io);
+        assert_eq!(map.page(0x806000), Page::Io);
+        assert_eq!(map.page(0x008000), Page::Rom(0));
+        assert_eq!(map.page(0x80A000), Page::Rom(0x2000));
+        assert_eq!(map.page(0x01E000), Page::Rom(0xE000));
+        assert_eq!(map.page(0x408000), Page::Rom(0x200000));
+        assert_eq!(map.page(0x400000), Page::Rom(0x200000));
+        assert_eq!(map.page(0xC08000), Page::Rom(0x200000));
+        assert_eq!(map.page(0x700000), Page::Sram(0));
+        assert_eq!(map.page(0x716000), Page::Sram(0xE000));
+        assert_eq!(map.page(0xF00000), Page::Sram(0));
+        assert_eq!(map.page(0x708000), Page::Rom(0x380000));
+    }
+
+    #[test]
+    fn hirom_pages() {
+        let map = MemoryMap::new(&MapMode::HiROM);
+        assert_eq!(map.page(0x008000), Page::Rom(0x8000));
+        assert_eq!(map.page(0xC00000), Page::Rom(0));
+        assert_eq!(map.page(0x412000), Page::Rom(0x12000));
+        assert_eq!(map.page(0x7E0000), Page::Ram(0));
+        assert_eq!(map.page(0x306000), Page::Sram(0x20000));
+        assert_eq!(map.page(0xA06000), Page::Sram(0));
+        assert_eq!(map.page(0x304000), Page::Io);
+        assert_eq!(map.page(0x700000), Page::Rom(0x300000));
+
+        let map = MemoryMap::new(&MapMode::ExHiROM);
+        assert_eq!(map.page(0xC00000), Page::Rom(0));
+        assert_eq!(map.page(0x400000), Page::Rom(0x400000));
+        assert_eq!(map.page(0x008000), Page::Rom(0x408000));
+        assert_eq!(map.page(0x806000), Page::Sram(0));
+        assert_eq!(map.page(0x206000), Page::Io);
+    }
+
+    #[test]
+    fn mirroring() {
+        // Powers of two repeat
+        assert_eq!(mirror(0x280000, 0x200000), 0x080000);
+        assert_eq!(mirror(0x1234, 0x8000), 0x1234);
+        // 3 MB: the last megabyte repeats above itself
+        assert_eq!(mirror(0x2ABCDE, 0x300000), 0x2ABCDE);
+        assert_eq!(mirror(0x3ABCDE, 0x300000), 0x2ABCDE);
+        assert_eq!(mirror(0x5ABCDE, 0x300000), 0x1ABCDE);
+        // 1.5 MB
+        assert_eq!(mirror(0x180000, 0x180000), 0x100000);
+        assert_eq!(mirror(0x250000, 0x180000), 0x050000);
+        assert_eq!(mirror(0x123, 0), 0);
+    }
+}
@@ -0,0 +1,382 @@
+use crate::cpu::{AddrMode, AddrMode::*, OpCode, OpCode::*};
+
+/// +1 cycle with a 16 bit accumulator. Immediate operands grow by a byte.
+pub const M16: u8 = 0x01;
+/// +2 cycles with a 16 bit accumulator, for read-modify-write
+pub const RMW16: u8 = 0x02;
+/// +1 cycle with 16 bit index registers. Immediate operands grow by a byte.
+pub const X16: u8 = 0x04;
+/// +1 cycle when DL is not zero
+pub const DL: u8 = 0x08;
+/// +1 cycle when indexing by X crosses a page or X is 16 bit
+pub const IX: u8 = 0x10;
+/// +1 cycle when indexing by Y crosses a page or Y is 16 bit
+pub const IY: u8 = 0x20;
+/// +1 cycle in native mode
+pub const NATIVE: u8 = 0x40;
+/// +1 cycle when taken, +1 more when taken across a page in emulation mode
+pub const BRANCH: u8 = 0x80;
+
+// Status register bits, in P byte order
+pub const N: u8 = 0x80;
+pub const V: u8 = 0x40;
+pub const M: u8 = 0x20;
+pub const X: u8 = 0x10;
+pub const D: u8 = 0x08;
+pub const I: u8 = 0x04;
+pub const Z: u8 = 0x02;
+pub const C: u8 = 0x01;
+
+/// Static decode, disassembly and timing information for one opcode byte
+#[derive(Debug)]
+pub struct OpcodeInfo {
+    pub opcode: OpCode,
+    pub mode: AddrMode,
+    /// Length in bytes with 8 bit registers
+    pub length: u8,
+    /// Cycles with 8 bit registers, before any penalties
+    pub cycles: u8,
+    /// Conditional cycle penalties, see `M16` through `BRANCH`
+    pub penalties: u8,
+    /// Status register bits the instruction can change
+    pub flags: u8,
+    /// Inherited from the 65C02, so direct page accesses wrap inside the page
+    /// in emulation mode
+    pub legacy: bool,
+}
+
+impl OpcodeInfo {
+    /// Can leave the accumulator a different width
+    pub fn changes_m(&self) -> bool {
+        self.flags & M != 0
+    }
+
+    /// Can leave the index registers a different width
+    pub fn changes_x(&self) -> bool {
+        self.flags & X != 0
+    }
+}
+
+const fn op(
+    opcode: OpCode,
+    mode: AddrMode,
+    length: u8,
+    cycles: u8,
+    penalties: u8,
+    flags: u8,
+    legacy: bool,
+) -> OpcodeInfo {
+    OpcodeInfo {
+        opcode,
+        mode,
+        length,
+        cycles,
+        penalties,
+        flags,
+        legacy,
+    }
+}
+
+pub static OPCODES: [OpcodeInfo; 256] = [
+    /* 00 */ op(BRK, Implied, 2, 7, NATIVE, D | I, true),
+    /* 01 */ op(ORA, IndexedDirectWord, 2, 6, M16 | DL, N | Z, true),
+    /* 02 */ op(COP, Immediate, 2, 7, NATIVE, D | I, false),
+    /* 03 */ op(ORA, Stack, 2, 4, M16, N | Z, true),
+    /* 04 */ op(TSB, Direct, 2, 5, RMW16 | DL, Z, true),
+    /* 05 */ op(ORA, Direct, 2, 3, M16 | DL, N | Z, true),
+    /* 06 */ op(ASL, Direct, 2, 5, RMW16 | DL, N | Z | C, true),
+    /* 07 */ op(ORA, DirectSWord, 2, 6, M16 | DL, N | Z, true),
+    /* 08 */ op(PHP, Implied, 1, 3, 0, 0, true),
+    /* 09 */ op(ORA, Immediate, 2, 2, M16, N | Z, true),
+    /* 0A */ op(ASL, Accumulator, 1, 2, 0, N | Z | C, true),
+    /* 0B */ op(PHD, Implied, 1, 4, 0, 0, false),
+    /* 0C */ op(TSB, Absolute, 3, 6, RMW16, Z, true),
+    /* 0D */ op(ORA, Absolute, 3, 4, M16, N | Z, true),
+    /* 0E */ op(ASL, Absolute, 3, 6, RMW16, N | Z | C, true),
+    /* 0F */ op(ORA, Long, 4, 5, M16, N | Z, true),
+    /* 10 */ op(BPL, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* 11 */ op(ORA, DirectIndexedWord, 2, 5, M16 | DL | IY, N | Z, true),
+    /* 12 */ op(ORA, DirectWord, 2, 5, M16 | DL, N | Z, true),
+    /* 13 */ op(ORA, StackIndexed, 2, 7, M16, N | Z, true),
+    /* 14 */ op(TRB, Direct, 2, 5, RMW16 | DL, Z, true),
+    /* 15 */ op(ORA, DirectX, 2, 4, M16 | DL, N | Z, true),
+    /* 16 */ op(ASL, DirectX, 2, 6, RMW16 | DL, N | Z | C, true),
+    /* 17 */ op(ORA, DirectIndexedSWord, 2, 6, M16 | DL, N | Z, true),
+    /* 18 */ op(CLC, Implied, 1, 2, 0, C, true),
+    /* 19 */ op(ORA, AbsoluteY, 3, 4, M16 | IY, N | Z, true),
+    /* 1A */ op(INC, Accumulator, 1, 2, 0, N | Z, true),
+    /* 1B */ op(TCS, Implied, 1, 2, 0, 0, false),
+    /* 1C */ op(TRB, Absolute, 3, 6, RMW16, Z, true),
+    /* 1D */ op(ORA, AbsoluteX, 3, 4, M16 | IX, N | Z, true),
+    /* 1E */ op(ASL, AbsoluteX, 3, 7, RMW16, N | Z | C, true),
+    /* 1F */ op(ORA, LongX, 4, 5, M16, N | Z, true),
+    /* 20 */ op(JSR, Absolute, 3, 6, 0, 0, true),
+    /* 21 */ op(AND, IndexedDirectWord, 2, 6, M16 | DL, N | Z, true),
+    /* 22 */ op(JSL, Long, 4, 8, 0, 0, false),
+    /* 23 */ op(AND, Stack, 2, 4, M16, N | Z, true),
+    /* 24 */ op(BIT, Direct, 2, 3, M16 | DL, N | V | Z, true),
+    /* 25 */ op(AND, Direct, 2, 3, M16 | DL, N | Z, true),
+    /* 26 */ op(ROL, Direct, 2, 5, RMW16 | DL, N | Z | C, true),
+    /* 27 */ op(AND, DirectSWord, 2, 6, M16 | DL, N | Z, true),
+    /* 28 */ op(PLP, Implied, 1, 4, 0, N | V | M | X | D | I | Z | C, true),
+    /* 29 */ op(AND, Immediate, 2, 2, M16, N | Z, true),
+    /* 2A */ op(ROL, Accumulator, 1, 2, 0, N | Z | C, true),
+    /* 2B */ op(PLD, Implied, 1, 5, 0, N | Z, false),
+    /* 2C */ op(BIT, Absolute, 3, 4, M16, N | V | Z, true),
+    /* 2D */ op(AND, Absolute, 3, 4, M16, N | Z, true),
+    /* 2E */ op(ROL, Absolute, 3, 6, RMW16, N | Z | C, true),
+    /* 2F */ op(AND, Long, 4, 5, M16, N | Z, true),
+    /* 30 */ op(BMI, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* 31 */ op(AND, DirectIndexedWord, 2, 5, M16 | DL | IY, N | Z, true),
+    /* 32 */ op(AND, DirectWord, 2, 5, M16 | DL, N | Z, true),
+    /* 33 */ op(AND, StackIndexed, 2, 7, M16, N | Z, true),
+    /* 34 */ op(BIT, DirectX, 2, 4, M16 | DL, N | V | Z, true),
+    /* 35 */ op(AND, DirectX, 2, 4, M16 | DL, N | Z, true),
+    /* 36 */ op(ROL, DirectX, 2, 6, RMW16 | DL, N | Z | C, true),
+    /* 37 */ op(AND, DirectIndexedSWord, 2, 6, M16 | DL, N | Z, true),
+    /* 38 */ op(SEC, Implied, 1, 2, 0, C, true),
+    /* 39 */ op(AND, AbsoluteY, 3, 4, M16 | IY, N | Z, true),
+    /* 3A */ op(DEC, Accumulator, 1, 2, 0, N | Z, true),
+    /* 3B */ op(TSC, Implied, 1, 2, 0, N | Z, false),
+    /* 3C */ op(BIT, AbsoluteX, 3, 4, M16 | IX, N | V | Z, true),
+    /* 3D */ op(AND, AbsoluteX, 3, 4, M16 | IX, N | Z, true),
+    /* 3E */ op(ROL, AbsoluteX, 3, 7, RMW16, N | Z | C, true),
+    /* 3F */ op(AND, LongX, 4, 5, M16, N | Z, true),
+    /* 40 */
+    op(
+        RTI,
+        Implied,
+        1,
+        6,
+        NATIVE,
+        N | V | M | X | D | I | Z | C,
+        true,
+    ),
+    /* 41 */ op(EOR, IndexedDirectWord, 2, 6, M16 | DL, N | Z, true),
+    /* 42 */ op(WDM, Immediate, 2, 2, 0, 0, false),
+    /* 43 */ op(EOR, Stack, 2, 4, M16, N | Z, true),
+    /* 44 */ op(MVP, SourceDestination, 3, 7, 0, 0, false),
+    /* 45 */ op(EOR, Direct, 2, 3, M16 | DL, N | Z, true),
+    /* 46 */ op(LSR, Direct, 2, 5, RMW16 | DL, N | Z | C, true),
+    /* 47 */ op(EOR, DirectSWord, 2, 6, M16 | DL, N | Z, true),
+    /* 48 */ op(PHA, Implied, 1, 3, M16, 0, true),
+    /* 49 */ op(EOR, Immediate, 2, 2, M16, N | Z, true),
+    /* 4A */ op(LSR, Accumulator, 1, 2, 0, N | Z | C, true),
+    /* 4B */ op(PHK, Implied, 1, 3, 0, 0, false),
+    /* 4C */ op(JMP, Absolute, 3, 3, 0, 0, true),
+    /* 4D */ op(EOR, Absolute, 3, 4, M16, N | Z, true),
+    /* 4E */ op(LSR, Absolute, 3, 6, RMW16, N | Z | C, true),
+    /* 4F */ op(EOR, Long, 4, 5, M16, N | Z, true),
+    /* 50 */ op(BVC, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* 51 */ op(EOR, DirectIndexedWord, 2, 5, M16 | DL | IY, N | Z, true),
+    /* 52 */ op(EOR, DirectWord, 2, 5, M16 | DL, N | Z, true),
+    /* 53 */ op(EOR, StackIndexed, 2, 7, M16, N | Z, true),
+    /* 54 */ op(MVN, SourceDestination, 3, 7, 0, 0, false),
+    /* 55 */ op(EOR, DirectX, 2, 4, M16 | DL, N | Z, true),
+    /* 56 */ op(LSR, DirectX, 2, 6, RMW16 | DL, N | Z | C, true),
+    /* 57 */ op(EOR, DirectIndexedSWord, 2, 6, M16 | DL, N | Z, true),
+    /* 58 */ op(CLI, Implied, 1, 2, 0, I, true),
+    /* 59 */ op(EOR, AbsoluteY, 3, 4, M16 | IY, N | Z, true),
+    /* 5A */ op(PHY, Implied, 1, 3, X16, 0, true),
+    /* 5B */ op(TCD, Implied, 1, 2, 0, N | Z, false),
+    /* 5C */ op(JMP, Long, 4, 4, 0, 0, true),
+    /* 5D */ op(EOR, AbsoluteX, 3, 4, M16 | IX, N | Z, true),
+    /* 5E */ op(LSR, AbsoluteX, 3, 7, RMW16, N | Z | C, true),
+    /* 5F */ op(EOR, LongX, 4, 5, M16, N | Z, true),
+    /* 60 */ op(RTS, Implied, 1, 6, 0, 0, true),
+    /* 61 */ op(ADC, IndexedDirectWord, 2, 6, M16 | DL, N | V | Z | C, true),
+    /* 62 */ op(PER, Immediate, 3, 6, 0, 0, false),
+    /* 63 */ op(ADC, Stack, 2, 4, M16, N | V | Z | C, true),
+    /* 64 */ op(STZ, Direct, 2, 3, M16 | DL, 0, true),
+    /* 65 */ op(ADC, Direct, 2, 3, M16 | DL, N | V | Z | C, true),
+    /* 66 */ op(ROR, Direct, 2, 5, RMW16 | DL, N | Z | C, true),
+    /* 67 */ op(ADC, DirectSWord, 2, 6, M16 | DL, N | V | Z | C, true),
+    /* 68 */ op(PLA, Implied, 1, 4, M16, N | Z, true),
+    /* 69 */ op(ADC, Immediate, 2, 2, M16, N | V | Z | C, true),
+    /* 6A */ op(ROR, Accumulator, 1, 2, 0, N | Z | C, true),
+    /* 6B */ op(RTL, Implied, 1, 6, 0, 0, false),
+    /* 6C */ op(JMP, AbsoluteIndirectWord, 3, 5, 0, 0, true),
+    /* 6D */ op(ADC, Absolute, 3, 4, M16, N | V | Z | C, true),
+    /* 6E */ op(ROR, Absolute, 3, 6, RMW16, N | Z | C, true),
+    /* 6F */ op(ADC, Long, 4, 5, M16, N | V | Z | C, true),
+    /* 70 */ op(BVS, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* 71 */
+    op(
+        ADC,
+        DirectIndexedWord,
+        2,
+        5,
+        M16 | DL | IY,
+        N | V | Z | C,
+        true,
+    ),
+    /* 72 */ op(ADC, DirectWord, 2, 5, M16 | DL, N | V | Z | C, true),
+    /* 73 */ op(ADC, StackIndexed, 2, 7, M16, N | V | Z | C, true),
+    /* 74 */ op(STZ, DirectX, 2, 4, M16 | DL, 0, true),
+    /* 75 */ op(ADC, DirectX, 2, 4, M16 | DL, N | V | Z | C, true),
+    /* 76 */ op(ROR, DirectX, 2, 6, RMW16 | DL, N | Z | C, true),
+    /* 77 */ op(ADC, DirectIndexedSWord, 2, 6, M16 | DL, N | V | Z | C, true),
+    /* 78 */ op(SEI, Implied, 1, 2, 0, I, true),
+    /* 79 */ op(ADC, AbsoluteY, 3, 4, M16 | IY, N | V | Z | C, true),
+    /* 7A */ op(PLY, Implied, 1, 4, X16, N | Z, true),
+    /* 7B */ op(TDC, Implied, 1, 2, 0, N | Z, false),
+    /* 7C */ op(JMP, AbsoluteIndexedIndirect, 3, 6, 0, 0, true),
+    /* 7D */ op(ADC, AbsoluteX, 3, 4, M16 | IX, N | V | Z | C, true),
+    /* 7E */ op(ROR, AbsoluteX, 3, 7, RMW16, N | Z | C, true),
+    /* 7F */ op(ADC, LongX, 4, 5, M16, N | V | Z | C, true),
+    /* 80 */ op(BRA, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* 81 */ op(STA, IndexedDirectWord, 2, 6, M16 | DL, 0, true),
+    /* 82 */ op(BRL, RelativeWord, 3, 4, 0, 0, false),
+    /* 83 */ op(STA, Stack, 2, 4, M16, 0, true),
+    /* 84 */ op(STY, Direct, 2, 3, X16 | DL, 0, true),
+    /* 85 */ op(STA, Direct, 2, 3, M16 | DL, 0, true),
+    /* 86 */ op(STX, Direct, 2, 3, X16 | DL, 0, true),
+    /* 87 */ op(STA, DirectSWord, 2, 6, M16 | DL, 0, true),
+    /* 88 */ op(DEY, Implied, 1, 2, 0, N | Z, true),
+    /* 89 */ op(BIT, Immediate, 2, 2, M16, Z, true),
+    /* 8A */ op(TXA, Implied, 1, 2, 0, N | Z, true),
+    /* 8B */ op(PHB, Implied, 1, 3, 0, 0, false),
+    /* 8C */ op(STY, Absolute, 3, 4, X16, 0, true),
+    /* 8D */ op(STA, Absolute, 3, 4, M16, 0, true),
+    /* 8E */ op(STX, Absolute, 3, 4, X16, 0, true),
+    /* 8F */ op(STA, Long, 4, 5, M16, 0, true),
+    /* 90 */ op(BCC, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* 91 */ op(STA, DirectIndexedWord, 2, 6, M16 | DL, 0, true),
+    /* 92 */ op(STA, DirectWord, 2, 5, M16 | DL, 0, true),
+    /* 93 */ op(STA, StackIndexed, 2, 7, M16, 0, true),
+    /* 94 */ op(STY, DirectX, 2, 4, X16 | DL, 0, true),
+    /* 95 */ op(STA, DirectX, 2, 4, M16 | DL, 0, true),
+    /* 96 */ op(STX, DirectY, 2, 4, X16 | DL, 0, true),
+    /* 97 */ op(STA, DirectIndexedSWord, 2, 6, M16 | DL, 0, true),
+    /* 98 */ op(TYA, Implied, 1, 2, 0, N | Z, true),
+    /* 99 */ op(STA, AbsoluteY, 3, 5, M16, 0, true),
+    /* 9A */ op(TXS, Implied, 1, 2, 0, 0, true),
+    /* 9B */ op(TXY, Implied, 1, 2, 0, N | Z, false),
+    /* 9C */ op(STZ, Absolute, 3, 4, M16, 0, true),
+    /* 9D */ op(STA, AbsoluteX, 3, 5, M16, 0, true),
+    /* 9E */ op(STZ, AbsoluteX, 3, 5, M16, 0, true),
+    /* 9F */ op(STA, LongX, 4, 5, M16, 0, true),
+    /* A0 */ op(LDY, Immediate, 2, 2, X16, N | Z, true),
+    /* A1 */ op(LDA, IndexedDirectWord, 2, 6, M16 | DL, N | Z, true),
+    /* A2 */ op(LDX, Immediate, 2, 2, X16, N | Z, true),
+    /* A3 */ op(LDA, Stack, 2, 4, M16, N | Z, true),
+    /* A4 */ op(LDY, Direct, 2, 3, X16 | DL, N | Z, true),
+    /* A5 */ op(LDA, Direct, 2, 3, M16 | DL, N | Z, true),
+    /* A6 */ op(LDX, Direct, 2, 3, X16 | DL, N | Z, true),
+    /* A7 */ op(LDA, DirectSWord, 2, 6, M16 | DL, N | Z, true),
+    /* A8 */ op(TAY, Implied, 1, 2, 0, N | Z, true),
+    /* A9 */ op(LDA, Immediate, 2, 2, M16, N | Z, true),
+    /* AA */ op(TAX, Implied, 1, 2, 0, N | Z, true),
+    /* AB */ op(PLB, Implied, 1, 4, 0, N | Z, false),
+    /* AC */ op(LDY, Absolute, 3, 4, X16, N | Z, true),
+    /* AD */ op(LDA, Absolute, 3, 4, M16, N | Z, true),
+    /* AE */ op(LDX, Absolute, 3, 4, X16, N | Z, true),
+    /* AF */ op(LDA, Long, 4, 5, M16, N | Z, true),
+    /* B0 */ op(BCS, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* B1 */ op(LDA, DirectIndexedWord, 2, 5, M16 | DL | IY, N | Z, true),
+    /* B2 */ op(LDA, DirectWord, 2, 5, M16 | DL, N | Z, true),
+    /* B3 */ op(LDA, StackIndexed, 2, 7, M16, N | Z, true),
+    /* B4 */ op(LDY, DirectX, 2, 4, X16 | DL, N | Z, true),
+    /* B5 */ op(LDA, DirectX, 2, 4, M16 | DL, N | Z, true),
+    /* B6 */ op(LDX, DirectY, 2, 4, X16 | DL, N | Z, true),
+    /* B7 */ op(LDA, DirectIndexedSWord, 2, 6, M16 | DL, N | Z, true),
+    /* B8 */ op(CLV, Implied, 1, 2, 0, V, true),
+    /* B9 */ op(LDA, AbsoluteY, 3, 4, M16 | IY, N | Z, true),
+    /* BA */ op(TSX, Implied, 1, 2, 0, N | Z, true),
+    /* BB */ op(TYX, Implied, 1, 2, 0, N | Z, false),
+    /* BC */ op(LDY, AbsoluteX, 3, 4, X16 | IX, N | Z, true),
+    /* BD */ op(LDA, AbsoluteX, 3, 4, M16 | IX, N | Z, true),
+    /* BE */ op(LDX, AbsoluteY, 3, 4, X16 | IY, N | Z, true),
+    /* BF */ op(LDA, LongX, 4, 5, M16, N | Z, true),
+    /* C0 */ op(CPY, Immediate, 2, 2, X16, N | Z | C, true),
+    /* C1 */ op(CMP, IndexedDirectWord, 2, 6, M16 | DL, N | Z | C, true),
+    /* C2 */
+    op(
+        REP,
+        Immediate,
+        2,
+        3,
+        0,
+        N | V | M | X | D | I | Z | C,
+        false,
+    ),
+    /* C3 */ op(CMP, Stack, 2, 4, M16, N | Z | C, true),
+    /* C4 */ op(CPY, Direct, 2, 3, X16 | DL, N | Z | C, true),
+    /* C5 */ op(CMP, Direct, 2, 3, M16 | DL, N | Z | C, true),
+    /* C6 */ op(DEC, Direct, 2, 5, RMW16 | DL, N | Z, true),
+    /* C7 */ op(CMP, DirectSWord, 2, 6, M16 | DL, N | Z | C, true),
+    /* C8 */ op(INY, Implied, 1, 2, 0, N | Z, true),
+    /* C9 */ op(CMP, Immediate, 2, 2, M16, N | Z | C, true),
+    /* CA */ op(DEX, Implied, 1, 2, 0, N | Z, true),
+    /* CB */ op(WAI, Implied, 1, 3, 0, 0, false),
+    /* CC */ op(CPY, Absolute, 3, 4, X16, N | Z | C, true),
+    /* CD */ op(CMP, Absolute, 3, 4, M16, N | Z | C, true),
+    /* CE */ op(DEC, Absolute, 3, 6, RMW16, N | Z, true),
+    /* CF */ op(CMP, Long, 4, 5, M16, N | Z | C, true),
+    /* D0 */ op(BNE, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* D1 */ op(CMP, DirectIndexedWord, 2, 5, M16 | DL | IY, N | Z | C, true),
+    /* D2 */ op(CMP, DirectWord, 2, 5, M16 | DL, N | Z | C, true),
+    /* D3 */ op(CMP, StackIndexed, 2, 7, M16, N | Z | C, true),
+    /* D4 */ op(PEI, Direct, 2, 6, DL, 0, false),
+    /* D5 */ op(CMP, DirectX, 2, 4, M16 | DL, N | Z | C, true),
+    /* D6 */ op(DEC, DirectX, 2, 6, RMW16 | DL, N | Z, true),
+    /* D7 */ op(CMP, DirectIndexedSWord, 2, 6, M16 | DL, N | Z | C, true),
+    /* D8 */ op(CLD, Implied, 1, 2, 0, D, true),
+    /* D9 */ op(CMP, AbsoluteY, 3, 4, M16 | IY, N | Z | C, true),
+    /* DA */ op(PHX, Implied, 1, 3, X16, 0, true),
+    /* DB */ op(STP, Implied, 1, 3, 0, 0, false),
+    /* DC */ op(JML, AbsoluteSWord, 3, 6, 0, 0, false),
+    /* DD */ op(CMP, AbsoluteX, 3, 4, M16 | IX, N | Z | C, true),
+    /* DE */ op(DEC, AbsoluteX, 3, 7, RMW16, N | Z, true),
+    /* DF */ op(CMP, LongX, 4, 5, M16, N | Z | C, true),
+    /* E0 */ op(CPX, Immediate, 2, 2, X16, N | Z | C, true),
+    /* E1 */ op(SBC, IndexedDirectWord, 2, 6, M16 | DL, N | V | Z | C, true),
+    /* E2 */
+    op(
+        SEP,
+        Immediate,
+        2,
+        3,
+        0,
+        N | V | M | X | D | I | Z | C,
+        false,
+    ),
+    /* E3 */ op(SBC, Stack, 2, 4, M16, N | V | Z | C, true),
+    /* E4 */ op(CPX, Direct, 2, 3, X16 | DL, N | Z | C, true),
+    /* E5 */ op(SBC, Direct, 2, 3, M16 | DL, N | V | Z | C, true),
+    /* E6 */ op(INC, Direct, 2, 5, RMW16 | DL, N | Z, true),
+    /* E7 */ op(SBC, DirectSWord, 2, 6, M16 | DL, N | V | Z | C, true),
+    /* E8 */ op(INX, Implied, 1, 2, 0, N | Z, true),
+    /* E9 */ op(SBC, Immediate, 2, 2, M16, N | V | Z | C, true),
+    /* EA */ op(NOP, Implied, 1, 2, 0, 0, true),
+    /* EB */ op(XBA, Implied, 1, 3, 0, N | Z, false),
+    /* EC */ op(CPX, Absolute, 3, 4, X16, N | Z | C, true),
+    /* ED */ op(SBC, Absolute, 3, 4, M16, N | V | Z | C, true),
+    /* EE */ op(INC, Absolute, 3, 6, RMW16, N | Z, true),
+    /* EF */ op(SBC, Long, 4, 5, M16, N | V | Z | C, true),
+    /* F0 */ op(BEQ, RelativeByte, 2, 2, BRANCH, 0, true),
+    /* F1 */
+    op(
+        SBC,
+        DirectIndexedWord,
+        2,
+        5,
+        M16 | DL | IY,
+        N | V | Z | C,
+        true,
+    ),
+    /* F2 */ op(SBC, DirectWord, 2, 5, M16 | DL, N | V | Z | C, true),
+    /* F3 */ op(SBC, StackIndexed, 2, 7, M16, N | V | Z | C, true),
+    /* F4 */ op(PEA, Immediate, 3, 5, 0, 0, false),
+    /* F5 */ op(SBC, DirectX, 2, 4, M16 | DL, N | V | Z | C, true),
+    /* F6 */ op(INC, DirectX, 2, 6, RMW16 | DL, N | Z, true),
+    /* F7 */ op(SBC, DirectIndexedSWord, 2, 6, M16 | DL, N | V | Z | C, true),
+    /* F8 */ op(SED, Implied, 1, 2, 0, D, true),
+    /* F9 */ op(SBC, AbsoluteY, 3, 4, M16 | IY, N | V | Z | C, true),
+    /* FA */ op(PLX, Implied, 1, 4, X16, N | Z, true),
+    /* FB */ op(XCE, Implied, 1, 2, 0, M | X | C, false),
+    /* FC */ op(JSR, AbsoluteIndexedIndirect, 3, 8, 0, 0, true),
+    /* FD */ op(SBC, AbsoluteX, 3, 4, M16 | IX, N | V | Z | C, true),
+    /* FE */ op(INC, AbsoluteX, 3, 7, RMW16, N | Z, true),
+    /* FF */ op(SBC, LongX, 4, 5, M16, N | V | Z | C, true),
+];
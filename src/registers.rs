@@ -1,4 +1,5 @@
 // use super::Console;
+use crate::alu::Alu;
 
 #[allow(non_snake_case)]
 #[derive(Clone, Debug, Default)]
@@ -30,10 +31,8 @@ pub struct MMIORegisters {
     pub TIMEUP: u8,
     pub HBVJOY: u8,
     pub RDIO: u8,
-    pub RDDIVL: u8,
-    pub RDDIVH: u8,
-    pub RDMPYL: u8,
-    pub RDMPYH: u8,
+    /// Multiply and divide unit, read back through RDDIVL/H and RDMPYL/H
+    pub alu: Alu,
 }
 
 #[allow(non_snake_case)]
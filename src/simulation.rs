@@ -0,0 +1,125 @@
+//! Speculative execution for debugger lookahead. Runs the CPU on the live
+//! console while RAM and SRAM writes go to a sparse overlay, then puts
+//! everything back when dropped. This costs a few kilobytes where cloning the
+//! console would copy all of RAM and ROM.
+
+use crate::cpu::CPU;
+use crate::hooks::Hooks;
+use crate::memory;
+use crate::registers::{DMARegisters, MMIORegisters};
+use crate::Console;
+use ahash::AHashMap;
+use std::ops::{Deref, DerefMut};
+
+/// A console that forgets everything done through it once dropped. Hooks
+/// don't fire while simulating.
+pub struct Simulation<'a> {
+    snes: &'a mut Console,
+    saved: Saved,
+}
+
+/// Console state to put back when a simulation ends
+struct Saved {
+    cpu: CPU,
+    mmio: MMIORegisters,
+    dma: DMARegisters,
+    master_clock: u64,
+    mdr: u8,
+    hooks: Hooks,
+    overlay: Option<AHashMap<memory::Target, u8>>,
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(snes: &'a mut Console) -> Simulation<'a> {
+        let overlay = snes.overlay.clone();
+        snes.overlay.get_or_insert_with(Default::default);
+        let saved = Saved {
+            cpu: snes.cpu.clone(),
+            mmio: snes.mmio.clone(),
+            dma: snes.dma.clone(),
+            master_clock: snes.master_clock,
+            mdr: snes.mdr,
+            hooks: std::mem::take(&mut snes.hooks),
+            overlay,
+        };
+        Simulation { snes, saved }
+    }
+}
+
+impl Deref for Simulation<'_> {
+    type Target = Console;
+
+    fn deref(&self) -> &Console {
+        self.snes
+    }
+}
+
+impl DerefMut for Simulation<'_> {
+    fn deref_mut(&mut self) -> &mut Console {
+        self.snes
+    }
+}
+
+impl Drop for Simulation<'_> {
+    fn drop(&mut self) {
+        let snes = &mut *self.snes;
+        let saved = &mut self.saved;
+        snes.cpu = saved.cpu.clone();
+        snes.mmio = std::mem::take(&mut saved.mmio);
+        snes.dma = std::mem::take(&mut saved.dma);
+        snes.master_clock = saved.master_clock;
+        snes.mdr = saved.mdr;
+        snes.hooks = std::mem::take(&mut saved.hooks);
+        snes.overlay = saved.overlay.take();
+    }
+}
+
+#[cfg(test)]
+mod simulation_tests {
+    use super::*;
+    use crate::cpu;
+    use crate::memory::Bus;
+    use crate::single_step::flat_console;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn writes_are_discarded() {
+        let mut snes = flat_console();
+        snes.cpu.PC = 0x1000;
+        snes.cpu.P.m = true;
+        // LDA #$42; STA $2000; STA $2001
+        snes.ram[0x1000..0x1008].copy_from_slice(&[0xA9, 0x42, 0x8D, 0x00, 0x20, 0x8D, 0x01, 0x20]);
+        let writes = Arc::new(Mutex::new(0));
+        let count = writes.clone();
+        snes.on_write(move |_| *count.lock().unwrap() += 1);
+
+        {
+            let mut sim = Simulation::new(&mut snes);
+            cpu::step(&mut sim).unwrap();
+            cpu::step(&mut sim).unwrap();
+            assert_eq!(sim.peek(0x002000).unwrap(), 0x42);
+            assert_eq!(sim.ram[0x2000], 0);
+
+            // Simulations nest
+            {
+                let mut inner = Simulation::new(&mut sim);
+                cpu::step(&mut inner).unwrap();
+                assert_eq!(inner.peek(0x002001).unwrap(), 0x42);
+            }
+            assert_eq!(sim.peek(0x002000).unwrap(), 0x42);
+            assert_eq!(sim.peek(0x002001).unwrap(), 0);
+            assert_eq!(sim.cpu.PC, 0x1005);
+        }
+        assert_eq!(snes.cpu.PC, 0x1000);
+        assert_eq!(snes.master_clock, 0);
+        assert_eq!(snes.peek(0x002000).unwrap(), 0);
+        assert!(snes.overlay.is_none());
+        assert_eq!(*writes.lock().unwrap(), 0);
+
+        // Hooks come back afterwards
+        cpu::step(&mut snes).unwrap();
+        cpu::step(&mut snes).unwrap();
+        assert_eq!(snes.ram[0x2000], 0x42);
+        assert_eq!(*writes.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,235 @@
+//! Runner for the community single-step 65C816 test vectors. Each JSON file
+//! holds the vectors for one opcode in one mode, such as `a9.n.json`. Point
+//! `SNES_SINGLE_STEP_DIR` at a directory of them and run
+//! `cargo test single_step -- --ignored --nocapture`.
+
+use crate::cartridge::*;
+use crate::cpu::{self, CPU};
+use crate::memory::Bus;
+use crate::Console;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct State {
+    pc: u16,
+    s: u16,
+    p: u8,
+    a: u16,
+    x: u16,
+    y: u16,
+    dbr: u8,
+    d: u16,
+    pbr: u8,
+    e: u8,
+    ram: Vec<(u32, u8)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestCase {
+    name: String,
+    initial: State,
+    #[serde(rename = "final")]
+    expected: State,
+    /// One entry per bus cycle. Only the count is checked.
+    cycles: Vec<serde_json::Value>,
+}
+
+pub(crate) fn flat_console() -> Console {
+    let cartridge = Cartridge {
+        header: RomHeader {
+            title: "Single step".to_string(),
+            map_mode: MapMode::LoROM,
+            rom_speed: RomSpeed::Slow,
+            extra_hardware: CartHardware::new(ExtraHardware::RomOnly, None),
+            rom_size: 0,
+            ram_size: 0,
+            country: Region::NTSC,
+            developer_id: 0,
+            rom_version: 0,
+            checksum_complement: 0,
+            checksum: 0,
+            interrupt_vectors: InterruptVectorTable {
+                cop: 0,
+                brk: 0,
+                abort: 0,
+                nmi: 0,
+                irq: 0,
+                cop_emu: 0,
+                brk_emu: 0,
+                abort_emu: 0,
+                nmi_emu: 0,
+                reset: 0,
+                irq_emu: 0,
+            },
+            expanded_header: None,
+        },
+        rom_data: Vec::new(),
+        sram: Vec::new(),
+    };
+    Console {
+        ram: vec![0; 0x1000000],
+        flat_memory: true,
+        ..Console::new(cartridge)
+    }
+}
+
+/// Run one vector on a flat memory console. Returns every mismatch found.
+fn run_case(snes: &mut Console, case: &TestCase) -> Result<(), Vec<String>> {
+    let initial = &case.initial;
+    snes.cpu = CPU::new();
+    snes.cpu.P.e = initial.e != 0;
+    snes.cpu.set_p(initial.p);
+    snes.cpu.A = initial.a;
+    snes.cpu.X = initial.x;
+    snes.cpu.Y = initial.y;
+    snes.cpu.S = initial.s;
+    snes.cpu.D = initial.d;
+    snes.cpu.DBR = initial.dbr;
+    snes.cpu.K = initial.pbr;
+    snes.cpu.PC = initial.pc;
+    for &(addr, data) in &initial.ram {
+        snes.ram[addr as usize] = data;
+    }
+
+    let pc = snes.cpu.get_pc();
+    let result = snes
+        .read(pc)
+        .map_err(|e| e.to_string())
+        .and_then(|op| cpu::decode_instruction(snes, op, pc).map_err(|e| e.to_string()))
+        .and_then(|instruction| {
+            cpu::execute_instruction(snes, &instruction).map_err(|e| e.to_string())
+        });
+
+    let expected = &case.expected;
+    let cpu = &snes.cpu;
+    let (mut errors, cycles) = match result {
+        Ok((_, cycles)) => (Vec::new(), cycles),
+        Err(e) => (vec![e], 0),
+    };
+    let mut check = |name: &str, got: u32, want: u32| {
+        if got != want {
+            errors.push(format!("{}: got {:X}, expected {:X}", name, got, want));
+        }
+    };
+    check("cycles", cycles as u32, case.cycles.len() as u32);
+    check("PC", cpu.PC as u32, expected.pc as u32);
+    check("K", cpu.K as u32, expected.pbr as u32);
+    check("S", cpu.S as u32, expected.s as u32);
+    check("P", cpu.p_byte() as u32, expected.p as u32);
+    check("E", cpu.P.e as u32, expected.e as u32);
+    check("A", cpu.A as u32, expected.a as u32);
+    check("X", cpu.X as u32, expected.x as u32);
+    check("Y", cpu.Y as u32, expected.y as u32);
+    check("D", cpu.D as u32, expected.d as u32);
+    check("DBR", cpu.DBR as u32, expected.dbr as u32);
+    for &(addr, data) in &expected.ram {
+        check(
+            &format!("${:06X}", addr),
+            snes.ram[addr as usize] as u32,
+            data as u32,
+        );
+    }
+
+    // Clear everything the vector touched for the next one
+    for &(addr, _) in initial.ram.iter().chain(&expected.ram) {
+        snes.ram[addr as usize] = 0;
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Run every vector in a file. Returns the pass and fail counts, and the
+/// first failure.
+fn run_file(path: &Path) -> (usize, usize, Option<String>) {
+    let reader = BufReader::new(File::open(path).unwrap());
+    let cases: Vec<TestCase> = serde_json::from_reader(reader).unwrap();
+    let mut snes = flat_console();
+    let mut passed = 0;
+    let mut failed = 0;
+    let mut first = None;
+    for case in &cases {
+        match run_case(&mut snes, case) {
+            Ok(()) => passed += 1,
+            Err(errors) => {
+                failed += 1;
+                first.get_or_insert_with(|| format!("{}: {}", case.name, errors.join(", ")));
+            }
+        }
+    }
+    (passed, failed, first)
+}
+
+#[test]
+#[ignore]
+fn single_step_suite() {
+    let dir = std::env::var("SNES_SINGLE_STEP_DIR")
+        .expect("SNES_SINGLE_STEP_DIR must point at the test vector directory");
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    let mut total_failed = 0;
+    for path in &files {
+        let (passed, failed, first) = run_file(path);
+        let name = path.file_stem().unwrap().to_string_lossy();
+        println!("{}: {}/{}", name, passed, passed + failed);
+        if let Some(first) = first {
+            println!("    {}", first);
+        }
+        total_failed += failed;
+    }
+    assert_eq!(total_failed, 0, "{} vectors failed", total_failed);
+}
+
+#[cfg(test)]
+mod single_step_tests {
+    use super::*;
+
+    const LDA_IMMEDIATE: &str = r#"{
+        "name": "a9 n 1",
+        "initial": {
+            "pc": 4096, "s": 511, "p": 48, "a": 65280, "x": 0, "y": 0,
+            "dbr": 0, "d": 0, "pbr": 18, "e": 0,
+            "ram": [[1183744, 169], [1183745, 128]]
+        },
+        "final": {
+            "pc": 4098, "s": 511, "p": 176, "a": 65408, "x": 0, "y": 0,
+            "dbr": 0, "d": 0, "pbr": 18, "e": 0,
+            "ram": [[1183744, 169], [1183745, 128]]
+        },
+        "cycles": [[1183744, 169, "dp-remx-"], [1183745, 128, "-p-remx-"]]
+    }"#;
+
+    #[test]
+    fn passing_vector() {
+        let case: TestCase = serde_json::from_str(LDA_IMMEDIATE).unwrap();
+        let mut snes = flat_console();
+        assert_eq!(run_case(&mut snes, &case), Ok(()));
+        assert_eq!(snes.ram[0x121000], 0);
+    }
+
+    #[test]
+    fn mismatches_are_reported() {
+        let mut case: TestCase = serde_json::from_str(LDA_IMMEDIATE).unwrap();
+        case.expected.a = 0x0080;
+        case.cycles.pop();
+        let errors = run_case(&mut flat_console(), &case).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "cycles: got 2, expected 1".to_string(),
+                "A: got FF80, expected 80".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Instruction trace in the bsnes layout, one line per instruction, so runs
+//! can be diffed against traces from other emulators, and a checker that runs
+//! against a reference trace until the first divergence.
+
+use crate::cpu::{self, AddrMode, CPUExecutionResult, InstructionContext, RunState, CPU};
+use crate::memory::Bus;
+use crate::Console;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+
+/// Master clocks per scanline
+const LINE_CLOCKS: u64 = 1364;
+/// Scanlines per NTSC frame
+const FRAME_LINES: u64 = 262;
+
+pub struct TraceLogger<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TraceLogger<W> {
+    pub fn new(out: W) -> TraceLogger<W> {
+        TraceLogger { out }
+    }
+
+    /// Step the CPU, logging the instruction if one executed. Interrupt entry
+    /// and idle cycles are not logged.
+    pub fn step(&mut self, snes: &mut Console) -> Result<(CPUExecutionResult, u8)> {
+        let line = pending_instruction(snes);
+        let interrupt = interrupt_due(&snes.cpu);
+        let result = cpu::step(snes)?;
+        if executed(&result.0, interrupt) {
+            writeln!(self.out, "{}", line?.0)?;
+        }
+        Ok(result)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Trace line and bytes for the instruction at PC. Decoding peeks, so hooks,
+/// the MDR and I/O side effects can't leak into the traced run.
+fn pending_instruction(snes: &Console) -> Result<(String, Vec<u8>)> {
+    let pc = snes.cpu.get_pc();
+    let instruction = cpu::peek_instruction(snes, pc)?;
+    let bytes = (0..instruction.length(snes.cpu.P.m, snes.cpu.P.x))
+        .map(|i| {
+            let addr = pc & 0xFF0000 | (pc as u16).wrapping_add(i as u16) as u32;
+            snes.peek(addr).unwrap_or(0)
+        })
+        .collect();
+    Ok((trace_line(snes, &instruction), bytes))
+}
+
+/// Whether the next step enters an interrupt rather than executing
+fn interrupt_due(cpu: &CPU) -> bool {
+    cpu.nmi_pending || (cpu.irq_pending && !cpu.P.i)
+}
+
+/// Whether a step ran an instruction. BRK and COP report an interrupt too.
+fn executed(result: &CPUExecutionResult, interrupt_due: bool) -> bool {
+    match result {
+        CPUExecutionResult::Idle => false,
+        CPUExecutionResult::Interrupt => !interrupt_due,
+        _ => true,
+    }
+}
+
+/// Format the trace line for an instruction about to execute. V and H are
+/// derived from the master clock until there is a PPU to count them.
+pub fn trace_line(snes: &Console, instruction: &InstructionContext) -> String {
+    let cpu = &snes.cpu;
+    // Bit 4 is B in emulation mode, shown set the way PHP pushes it
+    let p = cpu.p_byte() | (cpu.P.e as u8) << 4;
+    let flags: String = "NVMXDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if p & (0x80 >> i) != 0 {
+                c
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect();
+    let line = snes.master_clock / LINE_CLOCKS;
+    format!(
+        "{:06x} {:<23} A:{:04x} X:{:04x} Y:{:04x} S:{:04x} D:{:04x} DB:{:02x} {} V:{:3} H:{:3}",
+        instruction.inst_addr,
+        disassemble(snes, instruction),
+        cpu.A,
+        cpu.X,
+        cpu.Y,
+        cpu.S,
+        cpu.D,
+        cpu.DBR,
+        flags,
+        line % FRAME_LINES,
+        snes.master_clock % LINE_CLOCKS / 4,
+    )
+}
+
+/// Registers from one trace line. Disassembly and timing are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceState {
+    pub pc: u32,
+    pub a: u16,
+    pub x: u16,
+    pub y: u16,
+    pub s: u16,
+    pub d: u16,
+    pub db: u8,
+    pub p: u8,
+}
+
+impl TraceState {
+    /// Parse a bsnes or Mesen trace line. Returns None for lines that are not
+    /// instructions.
+    pub fn parse(line: &str) -> Option<TraceState> {
+        let pc = line.split_whitespace().next()?.replace(':', "");
+        let field = |name: &str| {
+            line.split_whitespace()
+                .find_map(|token| token.strip_prefix(name))
+                .and_then(|value| u16::from_str_radix(value, 16).ok())
+        };
+        // Flags are the token spelling out NVMXDIZC, upper case when set
+        let p = line.split_whitespace().find_map(|token| {
+            let token = token.strip_prefix("P:").unwrap_or(token);
+            if token.len() != 8 || !token.eq_ignore_ascii_case("NVMXDIZC") {
+                return None;
+            }
+            Some(
+                token
+                    .bytes()
+                    .fold(0, |p, c| p << 1 | c.is_ascii_uppercase() as u8),
+            )
+        })?;
+        Some(TraceState {
+            pc: u32::from_str_radix(&pc, 16).ok()?,
+            a: field("A:")?,
+            x: field("X:")?,
+            y: field("Y:")?,
+            s: field("S:")?,
+            d: field("D:")?,
+            db: field("DB:")? as u8,
+            p,
+        })
+    }
+}
+
+/// The first instruction that differed from the reference trace
+#[derive(Debug)]
+pub struct Divergence {
+    /// Line number in the reference trace, from 1
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+    /// Trace lines for the instructions before the divergence
+    pub history: Vec<String>,
+    /// CPU state before the diverging instruction executed
+    pub cpu: CPU,
+    pub master_clock: u64,
+    /// Bytes of the diverging instruction
+    pub bytes: Vec<u8>,
+    /// CPU cycles the diverging instruction took
+    pub cycles: u8,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Diverged from reference at line {}", self.line)?;
+        for line in &self.history {
+            writeln!(f, "  {}", line)?;
+        }
+        writeln!(f, "expected: {}", self.expected)?;
+        writeln!(f, "actual:   {}", self.actual)?;
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        writeln!(
+            f,
+            "Instruction: {} ({} cycles)",
+            bytes.join(" "),
+            self.cycles
+        )?;
+        writeln!(f, "Master clock: {}", self.master_clock)?;
+        write!(f, "{}", self.cpu)
+    }
+}
+
+/// Run until the executed instructions stop matching the reference trace, or
+/// the trace ends. Keeps the last `history` lines for the report.
+pub fn compare<R: BufRead>(
+    snes: &mut Console,
+    reference: R,
+    history: usize,
+) -> Result<Option<Divergence>> {
+    let mut recent = VecDeque::with_capacity(history + 1);
+    for (i, expected) in reference.lines().enumerate() {
+        let expected = expected?;
+        let Some(expected_state) = TraceState::parse(&expected) else {
+            continue;
+        };
+        // Skip interrupt entry and idle cycles until an instruction runs
+        let (actual, bytes, cycles, cpu, clock) = loop {
+            let cpu = snes.cpu.clone();
+            let clock = snes.master_clock;
+            let pending = pending_instruction(snes);
+            let interrupt = interrupt_due(&snes.cpu);
+            let (result, cycles) = cpu::step(snes)?;
+            if executed(&result, interrupt) {
+                let (line, bytes) = pending?;
+                break (line, bytes, cycles, cpu, clock);
+            }
+            if snes.cpu.state != RunState::Running && !snes.cpu.nmi_pending && !snes.cpu.irq_pending
+            {
+                bail!(
+                    "CPU {:?} with nothing to wake it at reference line {}",
+                    snes.cpu.state,
+                    i + 1
+                );
+            }
+        };
+        if TraceState::parse(&actual).as_ref() != Some(&expected_state) {
+            return Ok(Some(Divergence {
+                line: i + 1,
+                expected,
+                actual,
+                history: recent.into(),
+                cpu,
+                master_clock: clock,
+                bytes,
+                cycles,
+            }));
+        }
+        recent.push_back(actual);
+        if recent.len() > history {
+            recent.pop_front();
+        }
+    }
+    Ok(None)
+}
+
+/// Disassemble in bsnes syntax, with the effective address in brackets for
+/// memory operands
+fn disassemble(snes: &Console, instruction: &InstructionContext) -> String {
+    let addr = instruction.inst_addr;
+    let length = instruction.length(snes.cpu.P.m, snes.cpu.P.x);
+    // Operands wrap inside the program bank
+    let bytes: Vec<u8> = (1..length)
+        .map(|i| {
+            let byte_addr = addr & 0xFF0000 | (addr as u16).wrapping_add(i as u16) as u32;
+            snes.peek(byte_addr).unwrap_or(0)
+        })
+        .collect();
+    let operand = instruction.operand_text(&bytes, |_| None).to_lowercase();
+    let mnemonic = instruction.opcode.to_string().to_lowercase();
+    let resolved = !matches!(
+        instruction.mode,
+        AddrMode::Implied
+            | AddrMode::Accumulator
+            | AddrMode::Immediate
+            | AddrMode::RelativeByte
+            | AddrMode::RelativeWord
+            | AddrMode::SourceDestination
+    );
+    match (operand.is_empty(), resolved) {
+        (true, _) => mnemonic,
+        (false, true) => format!("{} {} [{:06x}]", mnemonic, operand, instruction.data_addr),
+        (false, false) => format!("{} {}", mnemonic, operand),
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+    use crate::single_step::flat_console;
+
+    fn load(snes: &mut Console, addr: usize, bytes: &[u8]) {
+        snes.ram[addr..addr + bytes.len()].copy_from_slice(bytes);
+    }
+
+    #[test]
+    fn formats_bsnes_line() {
+        let mut snes = flat_console();
+        snes.cpu.P.e = false;
+        snes.cpu.set_p(0x34);
+        snes.cpu.K = 0x00;
+        snes.cpu.PC = 0x8000;
+        snes.cpu.X = 0x0002;
+        snes.cpu.DBR = 0x7E;
+        snes.cpu.S = 0x01FF;
+        load(&mut snes, 0x8000, &[0xBD, 0x34, 0x12]);
+        let instruction = cpu::peek_instruction(&snes, 0x008000).unwrap();
+        assert_eq!(
+            trace_line(&snes, &instruction),
+            "008000 lda $1234,x [7e1236]    A:0000 X:0002 Y:0000 S:01ff D:0000 DB:7e nvMXdIzc V:  0 H:  0"
+        );
+    }
+
+    #[test]
+    fn emulation_mode_shows_b() {
+        let mut snes = flat_console();
+        snes.cpu.P.e = true;
+        snes.cpu.set_p(0x24);
+        snes.cpu.PC = 0x8000;
+        snes.cpu.S = 0x01FF;
+        load(&mut snes, 0x8000, &[0xEA]);
+        let instruction = cpu::peek_instruction(&snes, 0x008000).unwrap();
+        let line = trace_line(&snes, &instruction);
+        assert!(line.ends_with("nvMXdIzc V:  0 H:  0"), "{line}");
+
+        let reference = "008000 nop                     A:0000 X:0000 Y:0000 S:01ff D:0000 DB:00 nvMXdIzc V:  0 H:  0";
+        assert_eq!(TraceState::parse(&line), TraceState::parse(reference));
+    }
+
+    #[test]
+    fn operand_syntax() {
+        let mut snes = flat_console();
+        snes.cpu.P.e = false;
+        snes.cpu.set_p(0x00);
+        snes.cpu.PC = 0x1000;
+        let cases: [(&[u8], &str); 5] = [
+            (&[0xA9, 0x34, 0x12], "lda #$1234"),
+            (&[0x54, 0x7E, 0x7F], "mvn $7f,$7e"),
+            (&[0xB7, 0x10], "lda [$10],y [000000]"),
+            (&[0x80, 0xFE], "bra $1000"),
+            (&[0xEA], "nop"),
+        ];
+        for (bytes, text) in cases {
+            load(&mut snes, 0x1000, bytes);
+            let instruction = cpu::decode_instruction(&mut snes, bytes[0], 0x001000).unwrap();
+            assert_eq!(disassemble(&snes, &instruction), text);
+        }
+    }
+
+    #[test]
+    fn logs_executed_instructions() {
+        let mut snes = flat_console();
+        snes.cpu.PC = 0x1000;
+        // NOP; BRK
+        load(&mut snes, 0x1000, &[0xEA, 0x00]);
+        let mut logger = TraceLogger::new(Vec::new());
+        logger.step(&mut snes).unwrap();
+        logger.step(&mut snes).unwrap();
+        let text = String::from_utf8(logger.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("001000 nop "));
+        assert!(lines[1].starts_with("001001 brk "));
+        assert!(lines[1].ends_with("V:  0 H:  3"));
+    }
+
+    #[test]
+    fn parses_reference_lines() {
+        let line = "008000 lda $1234,x [7e1236]    A:00ff X:0002 Y:0000 S:01ff D:0000 DB:7e nvMXdIzc V:  0 H:  0";
+        let state = TraceState::parse(line).unwrap();
+        assert_eq!(state.pc, 0x008000);
+        assert_eq!(state.a, 0x00FF);
+        assert_eq!(state.db, 0x7E);
+        assert_eq!(state.p, 0x34);
+        let mesen = "00:8000 $BD LDA $1234,X A:00FF X:0002 Y:0000 S:01FF D:0000 DB:7E P:nvMXdIzc";
+        assert_eq!(TraceState::parse(mesen), Some(state));
+        assert_eq!(TraceState::parse("NMI"), None);
+    }
+
+    #[test]
+    fn stops_at_first_divergence() {
+        let mut snes = flat_console();
+        snes.cpu.PC = 0x1000;
+        // INX; INX; INX; INX
+        load(&mut snes, 0x1000, &[0xE8, 0xE8, 0xE8, 0xE8]);
+        let mut logger = TraceLogger::new(Vec::new());
+        for _ in 0..4 {
+            logger.step(&mut snes).unwrap();
+        }
+        let reference = String::from_utf8(logger.into_inner()).unwrap();
+
+        let mut snes = flat_console();
+        snes.cpu.PC = 0x1000;
+        load(&mut snes, 0x1000, &[0xE8, 0xE8, 0xE8, 0xE8]);
+        assert!(compare(&mut snes.clone(), reference.as_bytes(), 2)
+            .unwrap()
+            .is_none());
+
+        // DEX in place of the third INX
+        snes.ram[0x1002] = 0xCA;
+        let divergence = compare(&mut snes, reference.as_bytes(), 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(divergence.line, 4);
+        assert_eq!(divergence.history.len(), 1);
+        assert!(divergence.history[0].starts_with("001002 dex"));
+        assert_eq!(divergence.cpu.X, 0x0001);
+        assert_eq!(divergence.bytes, vec![0xE8]);
+        assert_eq!(divergence.cycles, 2);
+        assert!(divergence
+            .to_string()
+            .contains("Instruction: E8 (2 cycles)"));
+    }
+
+    #[test]
+    fn tracing_leaves_the_run_alone() {
+        let run = |trace: bool| {
+            let mut snes = flat_console();
+            snes.cpu.PC = 0x1000;
+            snes.cpu.P.m = true;
+            // LDA ($10); the pointer is read while decoding
+            load(&mut snes, 0x1000, &[0xB2, 0x10]);
+            load(&mut snes, 0x0010, &[0x00, 0x20]);
+            snes.ram[0x2000] = 0x42;
+            let reads = std::sync::Arc::new(std::sync::Mutex::new(0));
+            let count = reads.clone();
+            snes.on_read(move |_| *count.lock().unwrap() += 1);
+            if trace {
+                TraceLogger::new(Vec::new()).step(&mut snes).unwrap();
+            } else {
+                cpu::step(&mut snes).unwrap();
+            }
+            let reads = *reads.lock().unwrap();
+            (reads, snes.mdr, snes.cpu.A)
+        };
+        assert_eq!(run(true), run(false));
+    }
+}